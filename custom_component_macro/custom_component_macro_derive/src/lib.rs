@@ -32,6 +32,9 @@ fn impl_assemblage_component_macro(ast: &syn::DeriveInput) -> TokenStream {
                         }
                     });
             }
+            fn remove_from_entity(&self, world: &specs::World, entity: &specs::Entity) {
+                world.write_storage::<Self>().remove(*entity);
+            }
             fn name(&self) -> &'static str {
                 stringify!(#name)
             }
@@ -16,6 +16,7 @@ pub trait AssemblageComponent:
     //fn add_to_builder(&self, builder: &specs::EntityBuilder) -> specs::storage::InsertResult<()>;
 
     fn add_to_lazy_builder(&self, builder: &specs::world::LazyBuilder);
+    fn remove_from_entity(&self, world: &specs::World, entity: &specs::Entity);
     fn boxed_clone(&self) -> Box<dyn AssemblageComponent>;
     fn name(&self) -> &'static str;
 }
@@ -204,7 +204,7 @@ extern crate tuple_utils;
 #[macro_use]
 extern crate serde;
 
-#[cfg(feature = "serde")]
+//#[cfg(feature = "serde")]
 pub mod saveload;
 
 mod bitset;
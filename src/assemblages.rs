@@ -1,45 +1,164 @@
 use crate::prelude::*;
 use custom_component_macro::AssemblageComponent;
 use imgui::ImString;
-use specs::{world::EntitiesRes, world::LazyBuilder, Entity, LazyUpdate};
+use specs::{saveload::MarkedBuilder, world::EntitiesRes, world::LazyBuilder, Entity, LazyUpdate};
 use std::collections::HashMap;
 use std::fs::File;
-use std::io::prelude::*;
+use std::io::{self, prelude::*};
+use std::path::{Path, PathBuf};
 
-const TYPES_PATH: &str = "./src/data/types.json";
+pub const TYPES_PATH: &str = "./src/data/types.json";
+// the world everybody starts out with, and what the editor saves to when there's no slot.
 const INSTANCES_PATH: &str = "./src/data/instances.json";
 
+/// Where the world for a save slot is, or the starting world's if there's no slot.
+pub fn instances_path_for(slot: Option<&str>) -> PathBuf {
+    match slot {
+        Some(slot) => slot_path(slot),
+        None => PathBuf::from(INSTANCES_PATH),
+    }
+}
+
 #[allow(dead_code)]
 pub struct Assemblager {
     pub assemblages: HashMap<String, Vec<Box<AssemblageComponent>>>,
     pub components: HashMap<ImString, Box<AssemblageComponent>>,
+    // the save slot the world is loaded from and saved to, if any.
+    pub slot: Option<String>,
 }
+/// Parses an entity's (or a type's) components, reporting each one that can't be parsed.
+/// The components are only handed back if all of them parsed,
+/// since an entity that's missing some would just be broken in some other, weirder way.
+fn parse_components(
+    components: serde_json::Value,
+    file: &str,
+    location: &str,
+    errors: &mut LoadErrors,
+) -> Option<Vec<Box<AssemblageComponent>>> {
+    let components = match components {
+        serde_json::Value::Array(components) => components,
+        _ => {
+            errors.push(LoadError::new(
+                file,
+                location,
+                "Expected an array of components.",
+            ));
+            return None;
+        }
+    };
+
+    let mut parsed = Vec::with_capacity(components.len());
+    let mut all_parsed = true;
+    for (index, component) in components.into_iter().enumerate() {
+        // components look like {"Hitbox": {...}}
+        let name = component
+            .as_object()
+            .and_then(|object| object.keys().next())
+            .cloned()
+            .unwrap_or_else(|| "?".to_owned());
+
+        match serde_json::from_value::<Box<AssemblageComponent>>(component) {
+            Ok(component) => parsed.push(component),
+            Err(e) => {
+                all_parsed = false;
+                errors.push(
+                    LoadError::new(file, format!("{}[{}]", location, index), e)
+                        .with_component(name),
+                );
+            }
+        }
+    }
+
+    all_parsed.as_some(parsed)
+}
+
 #[allow(dead_code)]
 impl Assemblager {
-    pub fn new() -> Assemblager {
+    /// Types that can't be loaded are left out, and reported in `errors`.
+    pub fn new(errors: &mut LoadErrors) -> Assemblager {
         Self {
-            assemblages: {
-                let mut data = String::new();
-                File::open(TYPES_PATH)
-                    .unwrap()
-                    .read_to_string(&mut data)
-                    .unwrap();
-                serde_json::from_str(&data).unwrap()
-            },
+            assemblages: Self::load_types(errors),
             components: HashMap::new(),
+            slot: None,
         }
     }
 
+    /// Reads types.json again, i.e. when someone's changed it.
+    pub fn reload_types(&mut self, errors: &mut LoadErrors) {
+        self.assemblages = Self::load_types(errors);
+    }
+
+    fn load_types(errors: &mut LoadErrors) -> HashMap<String, Vec<Box<AssemblageComponent>>> {
+        let types = read_content_file(TYPES_PATH, errors)
+            .and_then(|data| {
+                serde_json::from_str::<serde_json::Map<String, serde_json::Value>>(&data)
+                    .map_err(|e| errors.push(LoadError::from_json_error(TYPES_PATH, e)))
+                    .ok()
+            })
+            .unwrap_or_default();
+
+        types
+            .into_iter()
+            .filter_map(|(key, components)| {
+                let location = format!("{:?}", key);
+                Some((
+                    key,
+                    parse_components(components, TYPES_PATH, &location, errors)?,
+                ))
+            })
+            .collect()
+    }
+
+    /// Where the world gets saved to.
+    pub fn instances_path(&self) -> PathBuf {
+        instances_path_for(self.slot.as_ref().map(String::as_str))
+    }
+
+    /// One of the components a type in types.json is built with, if it has one like that.
+    pub fn component<T: AssemblageComponent>(&self, assemblage_key: &str) -> Option<&T> {
+        self.assemblages
+            .get(assemblage_key)?
+            .iter()
+            .find_map(|component| component.downcast_ref::<T>())
+    }
+
     pub fn register_component<T: AssemblageComponent>(&mut self, component: T) {
         self.components
             .insert(ImString::new(component.name()), Box::new(component));
     }
 
-    fn get_json(world: &specs::World, entity: Entity) -> String {
+    /// Makes a registered component from its name, with `fields` (a json object)
+    /// replacing whichever of its default fields they have.
+    pub fn build_component(
+        &self,
+        name: &str,
+        fields: serde_json::Value,
+    ) -> Result<Box<AssemblageComponent>, String> {
+        let default = self
+            .components
+            .get(&ImString::new(name))
+            .ok_or_else(|| format!("There's no component called {}.", name))?;
+
+        // it serializes as {"Name": {...}}
+        let mut json = serde_json::to_value(default).map_err(|e| e.to_string())?;
+        match (json.get_mut(name), fields) {
+            (Some(serde_json::Value::Object(defaults)), serde_json::Value::Object(fields)) => {
+                defaults.extend(fields);
+            }
+            (Some(data), fields) => *data = fields,
+            (None, _) => return Err(format!("{} didn't serialize like a component.", name)),
+        }
+
+        serde_json::from_value(json).map_err(|e| format!("Couldn't make a {}: {}", name, e))
+    }
+
+    fn get_json(world: &specs::World, entity: Entity, id: u64) -> String {
         use specs::Join;
 
-        let mut output = String::new();
-        output.push('[');
+        // the marker is saved as the entity's id, not alongside its components.
+        const MARKER_JSON: &str = "{\"U64Marker\"";
+
+        let mut output = format!("{{\"id\":{},\"components\":[", id);
 
         let ps = world.read_resource::<PhysState>();
         for (phys, ent) in (&world.read_storage::<Phys>(), &world.entities()).join() {
@@ -54,67 +173,186 @@ impl Assemblager {
 
         for storage in world.any_storages().iter(&world.res) {
             if let Some(Some(serialized_data)) = storage.serialize(entity) {
-                output.push_str(",");
-                output.push_str(&serialized_data);
+                if !serialized_data.starts_with(MARKER_JSON) {
+                    output.push_str(",");
+                    output.push_str(&serialized_data);
+                }
             }
         }
 
-        output.push(']');
+        output.push_str("]}");
         output
     }
 
+    /// Writes out types.json and the world, once everything else this frame is done.
+    /// Anything that goes wrong is reported in the Dyon console.
     pub fn save_json(&self, lu: &LazyUpdate) {
-        use specs::Join;
+        let types = serde_json::to_string(&self.assemblages);
+        let instances_path = self.instances_path();
+        lu.exec(move |world| {
+            let saved = types
+                .map_err(io::Error::from)
+                .and_then(|types| std::fs::write(TYPES_PATH, types))
+                .and_then(|()| Self::write_save(world, &instances_path));
 
-        let mut file = File::create(TYPES_PATH).unwrap();
-        file.write_all(&serde_json::to_string(&self.assemblages).unwrap().as_bytes())
-            .unwrap();
+            match saved {
+                Ok(()) => {
+                    info!("Saved world to {}", instances_path.display());
 
-        lu.exec(move |world| {
-            let mut serialized_entities = String::new();
-            serialized_entities.push('[');
-            let assemblaged = world.read_storage::<Assemblaged>();
-            for (_, ent) in (&assemblaged, &world.entities()).join() {
-                if serialized_entities.len() != 1 {
-                    serialized_entities.push(',');
+                    // we already know what's in there, neither needs to be reloaded.
+                    let mut watcher = world.write_resource::<FileWatcher>();
+                    watcher.refresh(Path::new(TYPES_PATH));
+                    watcher.refresh(&instances_path);
                 }
-                serialized_entities.push_str(&Self::get_json(&world, ent));
+                Err(e) => world
+                    .write_resource::<DyonConsole>()
+                    .0
+                    .push_str(&format!("Couldn't save the world: {}\n", e)),
             }
-            serialized_entities.push(']');
-            let mut file = File::create(INSTANCES_PATH).unwrap();
-            file.write_all(&serialized_entities.as_bytes()).unwrap();
         });
     }
 
-    pub fn load_save(&self, world: &mut specs::World) {
+    fn write_save(world: &specs::World, instances_path: &Path) -> io::Result<()> {
+        use specs::Join;
+
+        let header = SaveHeader::new(world.read_resource::<LocalState>().play_time);
+
+        // which entities are being saved, so the dyon caches know whose to keep.
+        let mut saved_ids = std::collections::HashSet::new();
+
+        let mut serialized_entities = String::new();
+        serialized_entities.push('[');
+        let assemblaged = world.read_storage::<Assemblaged>();
+        let markers = world.read_storage::<U64Marker>();
+        for (_, marker, ent) in (&assemblaged, &markers, &world.entities()).join() {
+            if serialized_entities.len() != 1 {
+                serialized_entities.push(',');
+            }
+            serialized_entities.push_str(&Self::get_json(&world, ent, marker.id()));
+            saved_ids.insert(marker.id());
+        }
+        serialized_entities.push(']');
+
+        let dyon_caches = world
+            .read_resource::<DyonData>()
+            .save_caches(|id| saved_ids.contains(&id));
+        let script_timers = world
+            .read_resource::<ScriptScheduler>()
+            .saved(|id| saved_ids.contains(&id));
+        let quest_log = world.read_resource::<QuestLog>();
+
+        if let Some(directory) = instances_path.parent() {
+            std::fs::create_dir_all(directory)?;
+        }
+        let mut file = File::create(instances_path)?;
+        write!(
+            file,
+            "{{\"header\":{},\"entities\":{},\"dyon_caches\":{},\"script_timers\":{},\"quest_log\":{}}}",
+            serde_json::to_string(&header)?,
+            serialized_entities,
+            serde_json::to_string(&dyon_caches)?,
+            serde_json::to_string(&script_timers)?,
+            serde_json::to_string(&*quest_log)?
+        )
+    }
+
+    /// Loads the slot's save, or the starting world if that slot hasn't been saved to yet.
+    /// Old saves are migrated to the current format as they're loaded.
+    /// Entities that can't be loaded are skipped, and reported in `errors`.
+    pub fn load_save(&self, world: &mut specs::World, errors: &mut LoadErrors) -> SaveHeader {
+        Self::load_save_from(self.instances_path(), world, errors)
+    }
+
+    /// Like `load_save`, but for when the Assemblager is stuck in the World.
+    pub fn load_save_from(
+        mut instances_path: PathBuf,
+        world: &mut specs::World,
+        errors: &mut LoadErrors,
+    ) -> SaveHeader {
         use specs::{Builder, Join};
 
-        let mut file = File::open(INSTANCES_PATH).unwrap();
-        let mut contents = String::new();
-        file.read_to_string(&mut contents).unwrap();
+        if !instances_path.exists() {
+            info!(
+                "No save at {}, starting from {}",
+                instances_path.display(),
+                INSTANCES_PATH
+            );
+            instances_path = PathBuf::from(INSTANCES_PATH);
+        }
+
+        let file_name = instances_path.display().to_string();
+        let save = match read_content_file(&instances_path, errors).and_then(|contents| {
+            serde_json::from_str(&contents)
+                .map_err(|e| errors.push(LoadError::from_json_error(file_name.as_str(), e)))
+                .ok()
+        }) {
+            Some(json) => match migrate(json) {
+                Ok(save) => save,
+                Err(e) => {
+                    errors.push(LoadError::new(file_name.as_str(), "", e));
+                    return SaveHeader::new(0.0);
+                }
+            },
+            None => return SaveHeader::new(0.0),
+        };
 
         {
-            let entity_data: Vec<Vec<Box<AssemblageComponent>>> =
-                serde_json::from_str(&contents).unwrap();
             let lu = world.read_resource::<specs::world::LazyUpdate>();
             let ents = world.entities();
 
-            for components in entity_data.iter() {
+            for (index, entity) in save.entities.into_iter().enumerate() {
+                let location = format!("entities[{}]", index);
+                let SavedEntity { id, components } = match serde_json::from_value(entity) {
+                    Ok(entity) => entity,
+                    Err(e) => {
+                        errors.push(LoadError::new(file_name.as_str(), location, e));
+                        continue;
+                    }
+                };
+                let components = match parse_components(
+                    components,
+                    &file_name,
+                    &format!("{}.components", location),
+                    errors,
+                ) {
+                    Some(components) => components,
+                    None => continue,
+                };
+
                 let builder = lu.create_entity(&ents);
 
                 for c in components {
                     c.add_to_lazy_builder(&builder);
                 }
 
-                builder.build();
+                // they get back the same id they were saved with,
+                // so anything that refers to them still does.
+                let ent = builder.build();
+                lu.exec(move |world| {
+                    let marker = world
+                        .write_resource::<U64MarkerAllocator>()
+                        .allocate(ent, Some(id));
+                    world
+                        .write_storage::<U64Marker>()
+                        .insert(ent, marker)
+                        .unwrap();
+                });
             }
         }
 
         world.maintain();
+        world
+            .write_resource::<DyonData>()
+            .load_caches(save.dyon_caches);
+        *world.write_resource::<ScriptScheduler>() = save.script_timers;
+        *world.write_resource::<QuestLog>() = save.quest_log;
+
         let mut appearance_builders = world.write_storage::<AppearanceBuilder>();
         for mut appear_builder in (&mut appearance_builders).join() {
             appear_builder.built = false;
         }
+
+        save.header
     }
 
     pub fn draft<'a, 'b, 'c>(
@@ -131,9 +369,13 @@ impl Assemblager {
             c.add_to_lazy_builder(&builder);
         }
 
-        builder = builder.with(Assemblaged {
-            built_from: assemblage_key.to_string(),
-        });
+        builder = builder
+            .with(Assemblaged {
+                built_from: assemblage_key.to_string(),
+            })
+            // everything that's built gets a persistent id, so it can be referred to
+            // even after it's been saved and loaded, or entities around it have been deleted.
+            .marked::<U64Marker>();
 
         builder
     }
@@ -177,6 +419,20 @@ impl Assemblager {
     }
 }
 
+/// Finds the entity with this persistent id, so long as it's still around.
+pub fn entity_from_id(world: &specs::World, id: u64) -> Option<Entity> {
+    let ents = world.entities();
+    world
+        .read_resource::<U64MarkerAllocator>()
+        .retrieve_entity_internal(id)
+        .filter(|ent| ents.is_alive(*ent))
+}
+
+/// The persistent id of an entity, if it has one.
+pub fn id_of_entity(world: &specs::World, ent: Entity) -> Option<u64> {
+    world.read_storage::<U64Marker>().get(ent).map(Marker::id)
+}
+
 // ease of use thing so you don't have to pass LazyUpdate and EntitiesRes each and every time.
 #[allow(dead_code)]
 pub struct Spawner<'a> {
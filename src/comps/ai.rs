@@ -0,0 +1,305 @@
+use super::prelude::*;
+
+/// What an NPC does while it's in a state.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum Behaviour {
+    // stands around
+    Idle,
+    // walks to random spots no further than `radius` from home
+    Wander { radius: f32 },
+    // walks toward the target until it's `stop_distance` away
+    Follow { stop_distance: f32 },
+    // runs from the target until it's `safe_distance` away
+    Flee { safe_distance: f32 },
+    // walks back to where it started
+    ReturnHome,
+}
+impl Default for Behaviour {
+    fn default() -> Self {
+        Behaviour::Idle
+    }
+}
+impl Behaviour {
+    const NAMES: [&'static str; 5] = ["Idle", "Wander", "Follow", "Flee", "ReturnHome"];
+
+    fn index(&self) -> usize {
+        match self {
+            Behaviour::Idle => 0,
+            Behaviour::Wander { .. } => 1,
+            Behaviour::Follow { .. } => 2,
+            Behaviour::Flee { .. } => 3,
+            Behaviour::ReturnHome => 4,
+        }
+    }
+
+    fn from_index(index: usize) -> Self {
+        match index {
+            1 => Behaviour::Wander { radius: 3.0 },
+            2 => Behaviour::Follow { stop_distance: 1.0 },
+            3 => Behaviour::Flee { safe_distance: 5.0 },
+            4 => Behaviour::ReturnHome,
+            _ => Behaviour::Idle,
+        }
+    }
+}
+
+/// When an NPC should leave the state it's in.
+/// Distances are on the ground, and the target's whatever the AiBehaviour's `target` finds.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum Condition {
+    TargetWithin(f32),
+    // also true when there's no target around at all
+    TargetBeyond(f32),
+    // health is a fraction of the max, so 0.25 is a quarter health.
+    HealthBelow(f32),
+    HealthAbove(f32),
+    // seconds since the state was entered
+    After(f32),
+    AwayFromHome(f32),
+    AtHome,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Transition {
+    pub when: Condition,
+    pub to: String,
+}
+
+fn default_speed() -> f32 {
+    2.0
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct AiState {
+    pub name: String,
+    #[serde(default)]
+    pub behaviour: Behaviour,
+    #[serde(default = "default_speed")]
+    pub speed: f32,
+    // a Dyon function called on the entity as it enters this state,
+    // with the state it came `from` and the `state` it's now in.
+    #[serde(default)]
+    pub on_enter: String,
+    // checked in order, the first one that's true is taken.
+    #[serde(default)]
+    pub transitions: Vec<Transition>,
+}
+
+impl Default for AiState {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            behaviour: Behaviour::default(),
+            speed: default_speed(),
+            on_enter: String::new(),
+            transitions: Vec::new(),
+        }
+    }
+}
+
+/// Who an NPC follows, flees from, or measures distances to.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum AiTarget {
+    Player,
+    // the first entity with this in its ScriptingIds
+    ScriptingId(String),
+}
+impl Default for AiTarget {
+    fn default() -> Self {
+        AiTarget::Player
+    }
+}
+
+//ai behaviour component
+#[derive(
+    Default,
+    Component,
+    DevUiComponent,
+    CopyToOtherEntity,
+    AssemblageComponent,
+    Serialize,
+    Deserialize,
+    Clone,
+    Debug,
+)]
+#[storage(HashMapStorage)]
+pub struct AiBehaviour {
+    pub initial: String,
+    pub states: Vec<AiState>,
+    #[serde(default)]
+    pub target: AiTarget,
+    // where they return to and wander around, it's wherever they are when they first think.
+    #[serde(default)]
+    pub home: Option<[f32; 3]>,
+    // None until they've first thought, then they enter the initial state.
+    #[serde(default)]
+    pub current: Option<String>,
+    #[serde(skip)]
+    pub time_in_state: f32,
+    // where they're wandering to, and how long they've been trying to get there.
+    #[serde(skip)]
+    pub wander_goal: Option<([f32; 2], f32)>,
+}
+impl AiBehaviour {
+    pub fn find_state(&self, name: &str) -> Option<&AiState> {
+        self.states.iter().find(|state| state.name == name)
+    }
+
+    pub fn state(&self) -> Option<&AiState> {
+        self.find_state(self.current.as_ref()?)
+    }
+
+    /// Switches to another state, returning the event for its `on_enter` handler, if it has one.
+    /// States that don't exist can't be switched to.
+    pub fn enter(&mut self, state: &str) -> Result<Option<ScriptEvent>, String> {
+        let on_enter = self
+            .find_state(state)
+            .ok_or_else(|| format!("There's no AI state called {}.", state))?
+            .on_enter
+            .clone();
+        let from = self.current.replace(state.to_owned()).unwrap_or_default();
+        self.time_in_state = 0.0;
+        self.wander_goal = None;
+
+        Ok(Some(on_enter).filter(|f| !f.is_empty()).map(|function| {
+            ScriptEvent {
+                function,
+                payload: ScriptPayload::None,
+            }
+            .clone_with_payload(vec![
+                ("from", ScriptPayload::Text(from)),
+                ("state", ScriptPayload::Text(state.to_owned())),
+            ])
+        }))
+    }
+}
+impl DevUiRender for AiBehaviour {
+    fn dev_ui_render(&mut self, ui: &imgui::Ui, _world: &specs::World) {
+        use imgui::*;
+
+        ui.text(im_str!("AiBehaviour"));
+        ui.text(im_str!(
+            "currently: {}",
+            self.current.as_ref().map_or("not started", |s| s.as_str())
+        ));
+
+        let mut initial = ImString::with_capacity(50);
+        initial.push_str(&self.initial);
+        if ui
+            .input_text(im_str!("initial state"), &mut initial)
+            .build()
+        {
+            self.initial = initial.to_str().to_owned();
+        }
+
+        // it's edited as json, like the states' transitions.
+        let mut target = ImString::with_capacity(100);
+        target.push_str(&serde_json::to_string(&self.target).unwrap());
+        if ui.input_text(im_str!("target"), &mut target).build() {
+            if let Ok(target) = serde_json::from_str(target.to_str()) {
+                self.target = target;
+            }
+        }
+
+        if ui.button(im_str!("Reset Home"), [85.0, 20.0]) {
+            self.home = None;
+        }
+
+        // every field is labelled with the state's index, so imgui can tell them apart.
+        let mut removed = None;
+        for (index, state) in self.states.iter_mut().enumerate() {
+            ui.separator();
+
+            let mut im_name = ImString::with_capacity(50);
+            im_name.push_str(&state.name);
+            if ui
+                .input_text(&ImString::new(format!("state##{}", index)), &mut im_name)
+                .build()
+            {
+                state.name = im_name.to_str().to_owned();
+            }
+
+            let im_names = Behaviour::NAMES
+                .iter()
+                .map(|name| ImString::new(*name))
+                .collect::<Vec<_>>();
+            let im_str_names = im_names.iter().map(ImStr::new).collect::<Vec<_>>();
+            let mut behaviour_index = state.behaviour.index() as i32;
+            if ui.combo(
+                &ImString::new(format!("behaviour##{}", index)),
+                &mut behaviour_index,
+                &im_str_names,
+                5,
+            ) && behaviour_index as usize != state.behaviour.index()
+            {
+                state.behaviour = Behaviour::from_index(behaviour_index as usize);
+            }
+
+            match &mut state.behaviour {
+                Behaviour::Wander { radius: distance }
+                | Behaviour::Follow {
+                    stop_distance: distance,
+                }
+                | Behaviour::Flee {
+                    safe_distance: distance,
+                } => {
+                    ui.input_float(&ImString::new(format!("distance##{}", index)), distance)
+                        .build();
+                }
+                Behaviour::Idle | Behaviour::ReturnHome => {}
+            }
+
+            ui.input_float(
+                &ImString::new(format!("speed##{}", index)),
+                &mut state.speed,
+            )
+            .build();
+
+            let mut on_enter = ImString::with_capacity(100);
+            on_enter.push_str(&state.on_enter);
+            if ui
+                .input_text(
+                    &ImString::new(format!("on enter##{}", index)),
+                    &mut on_enter,
+                )
+                .build()
+            {
+                state.on_enter = on_enter.to_str().to_owned();
+            }
+
+            let mut transitions = ImString::with_capacity(500);
+            transitions.push_str(&serde_json::to_string(&state.transitions).unwrap());
+            if ui
+                .input_text(
+                    &ImString::new(format!("transitions##{}", index)),
+                    &mut transitions,
+                )
+                .build()
+            {
+                if let Ok(transitions) = serde_json::from_str(transitions.to_str()) {
+                    state.transitions = transitions;
+                }
+            }
+
+            if ui.button(&ImString::new(format!("Remove##{}", index)), [85.0, 20.0]) {
+                removed = Some(index);
+            }
+        }
+
+        if let Some(index) = removed {
+            self.states.remove(index);
+        }
+
+        ui.separator();
+        if ui.button(im_str!("New State"), [85.0, 20.0]) {
+            let name = (self.states.len()..)
+                .map(|n| format!("state {}", n))
+                .find(|name| self.find_state(name).is_none())
+                .unwrap();
+            self.states.push(AiState {
+                name,
+                ..AiState::default()
+            });
+        }
+    }
+}
@@ -0,0 +1,166 @@
+use super::prelude::*;
+
+fn physical() -> String {
+    "physical".to_owned()
+}
+
+fn default_facing() -> [f32; 2] {
+    [1.0, 0.0]
+}
+
+//attack component
+#[derive(
+    Component,
+    DevUiComponent,
+    CopyToOtherEntity,
+    AssemblageComponent,
+    Serialize,
+    Deserialize,
+    Clone,
+    Debug,
+)]
+#[storage(HashMapStorage)]
+pub struct Attack {
+    // how far from the attacker's center things can be hit
+    pub range: f32,
+    // how wide the swing is, in degrees. 360 hits everything in range.
+    pub arc: f32,
+    // seconds between attacks
+    pub cooldown: f32,
+    pub damage: f32,
+    #[serde(default = "physical")]
+    pub kind: String,
+    // how hard the things that are hit are pushed away
+    #[serde(default)]
+    pub knockback: f32,
+    // if this is set, attacking throws this assemblage instead of swinging.
+    #[serde(default)]
+    pub projectile: Option<String>,
+    #[serde(skip)]
+    pub cooldown_left: f32,
+    // which way they're attacking, it follows them as they move around.
+    #[serde(skip, default = "default_facing")]
+    pub facing: [f32; 2],
+    // set when they should attack as soon as they can.
+    #[serde(skip)]
+    pub triggered: bool,
+}
+impl Default for Attack {
+    fn default() -> Self {
+        Self {
+            range: 1.0,
+            arc: 90.0,
+            cooldown: 0.5,
+            damage: 1.0,
+            kind: physical(),
+            knockback: 0.0,
+            projectile: None,
+            cooldown_left: 0.0,
+            facing: default_facing(),
+            triggered: false,
+        }
+    }
+}
+impl DevUiRender for Attack {
+    fn dev_ui_render(&mut self, ui: &imgui::Ui, _world: &specs::World) {
+        use imgui::*;
+
+        ui.text(im_str!("Attack"));
+        ui.input_float(im_str!("range"), &mut self.range).build();
+        ui.input_float(im_str!("arc (degrees)"), &mut self.arc)
+            .build();
+        ui.input_float(im_str!("cooldown"), &mut self.cooldown)
+            .build();
+        ui.input_float(im_str!("damage"), &mut self.damage).build();
+        ui.input_float(im_str!("knockback"), &mut self.knockback)
+            .build();
+
+        let mut kind = ImString::with_capacity(25);
+        kind.push_str(&self.kind);
+        if ui.input_text(im_str!("damage kind"), &mut kind).build() {
+            self.kind = kind.to_str().to_owned();
+        }
+
+        let mut projectile = ImString::with_capacity(50);
+        projectile.push_str(self.projectile.as_ref().map_or("", |s| s.as_str()));
+        if ui
+            .input_text(im_str!("projectile assemblage"), &mut projectile)
+            .build()
+        {
+            self.projectile = Some(projectile.to_str().to_owned()).filter(|s| !s.is_empty());
+        }
+
+        if ui.button(im_str!("Attack!"), [85.0, 20.0]) {
+            self.triggered = true;
+        }
+    }
+}
+
+//projectile component
+#[derive(
+    Component,
+    DevUiComponent,
+    CopyToOtherEntity,
+    AssemblageComponent,
+    Serialize,
+    Deserialize,
+    Clone,
+    Debug,
+)]
+#[storage(HashMapStorage)]
+pub struct Projectile {
+    pub speed: f32,
+    // seconds until it disappears, if it hasn't hit anything.
+    pub lifetime: f32,
+    // dealt to whatever it hits, as long as that has Health.
+    pub damage: f32,
+    #[serde(default = "physical")]
+    pub kind: String,
+    #[serde(default)]
+    pub knockback: f32,
+    // the persistent id of whoever threw it, it flies right through them.
+    #[serde(default)]
+    pub owner: Option<u64>,
+    #[serde(default)]
+    pub age: f32,
+    #[serde(default = "default_facing")]
+    pub direction: [f32; 2],
+}
+impl Default for Projectile {
+    fn default() -> Self {
+        Self {
+            speed: 8.0,
+            lifetime: 1.0,
+            damage: 1.0,
+            kind: physical(),
+            knockback: 0.0,
+            owner: None,
+            age: 0.0,
+            direction: default_facing(),
+        }
+    }
+}
+impl DevUiRender for Projectile {
+    fn dev_ui_render(&mut self, ui: &imgui::Ui, _world: &specs::World) {
+        use imgui::*;
+
+        ui.text(im_str!("Projectile"));
+        ui.input_float(im_str!("speed"), &mut self.speed).build();
+        ui.input_float(im_str!("lifetime"), &mut self.lifetime)
+            .build();
+        ui.input_float(im_str!("damage"), &mut self.damage).build();
+        ui.input_float(im_str!("knockback"), &mut self.knockback)
+            .build();
+
+        let mut kind = ImString::with_capacity(25);
+        kind.push_str(&self.kind);
+        if ui.input_text(im_str!("damage kind"), &mut kind).build() {
+            self.kind = kind.to_str().to_owned();
+        }
+
+        match self.owner {
+            Some(owner) => ui.text(im_str!("thrown by #{}", owner)),
+            None => ui.text(im_str!("thrown by nobody")),
+        }
+    }
+}
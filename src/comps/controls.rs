@@ -25,3 +25,23 @@ impl DevUiRender for MovementControls {
             .build();
     }
 }
+
+/// Pushes a body so that it ends up moving along `direction` at `speed`.
+/// It's how the player gets around, and NPCs walk the same way.
+pub fn steer_body(
+    body: &mut nphysics3d::object::RigidBody<f32>,
+    direction: &glm::TVec3<f32>,
+    speed: f32,
+) {
+    use nphysics3d::{
+        math::{Force, ForceType},
+        object::Body,
+    };
+
+    body.apply_force(
+        0,
+        &Force::linear(direction * speed - body.velocity().linear),
+        ForceType::Force,
+        true,
+    );
+}
@@ -56,3 +56,8 @@ impl DevUiRender for Explodeable {
         self.force.dev_ui_render(ui, world);
     }
 }
+
+/// Sets off an Explodeable the next time explosions happen, and is then removed.
+#[derive(Component, Default, Clone, Copy, Debug)]
+#[storage(NullStorage)]
+pub struct Detonate;
@@ -27,3 +27,205 @@ impl DevUiRender for Health {
         ui.input_float(im_str!("max"), &mut self.max).build();
     }
 }
+
+/// One bit of damage that's waiting to be taken.
+#[derive(Clone, Debug)]
+pub struct DamageHit {
+    // negative amounts heal, ignoring resistances and invulnerability.
+    pub amount: f32,
+    // the persistent id of whatever did it, if anything.
+    pub source: Option<u64>,
+    // i.e. "physical" or "fire", for Resistances.
+    pub kind: String,
+}
+
+/// All of the damage an entity's been dealt this tick, it's taken the next time damage is applied.
+/// Like ScriptEvents these don't stick around, so they aren't saved.
+#[derive(Component, Default, Clone, Debug)]
+#[storage(HashMapStorage)]
+pub struct Damage {
+    pub hits: Vec<DamageHit>,
+}
+impl Damage {
+    /// Adds a hit to whatever damage the entity's already been dealt this tick.
+    pub fn deal(damages: &mut specs::WriteStorage<Damage>, ent: specs::Entity, hit: DamageHit) {
+        match damages.get_mut(ent) {
+            Some(damage) => damage.hits.push(hit),
+            None => {
+                damages.insert(ent, Damage { hits: vec![hit] }).unwrap();
+            }
+        }
+    }
+}
+
+//resistances component
+#[derive(
+    Default,
+    Component,
+    DevUiComponent,
+    CopyToOtherEntity,
+    AssemblageComponent,
+    Serialize,
+    Deserialize,
+    Clone,
+    Debug,
+)]
+#[storage(HashMapStorage)]
+pub struct Resistances {
+    // damage of each kind is multiplied by these, so 0.5 means taking half of it.
+    // kinds that aren't in here aren't resisted at all.
+    pub multipliers: std::collections::BTreeMap<String, f32>,
+}
+impl Resistances {
+    pub fn multiplier(&self, kind: &str) -> f32 {
+        self.multipliers.get(kind).cloned().unwrap_or(1.0)
+    }
+}
+impl DevUiRender for Resistances {
+    fn dev_ui_render(&mut self, ui: &imgui::Ui, _world: &specs::World) {
+        use imgui::*;
+
+        ui.text(im_str!("Resistances"));
+
+        // edited as json, so new kinds can be typed in along with their multipliers.
+        let mut im_multipliers = ImString::with_capacity(200);
+        im_multipliers.push_str(&serde_json::to_string(&self.multipliers).unwrap());
+        if ui
+            .input_text(im_str!("multipliers"), &mut im_multipliers)
+            .build()
+        {
+            if let Ok(multipliers) = serde_json::from_str(im_multipliers.to_str()) {
+                self.multipliers = multipliers;
+            }
+        }
+    }
+}
+
+//invulnerability component
+#[derive(
+    Default,
+    Component,
+    DevUiComponent,
+    CopyToOtherEntity,
+    AssemblageComponent,
+    Serialize,
+    Deserialize,
+    Clone,
+    Debug,
+)]
+#[storage(HashMapStorage)]
+pub struct Invulnerability {
+    // how long nothing can hurt them after they've been hurt.
+    pub seconds: f32,
+    #[serde(skip)]
+    pub remaining: f32,
+}
+impl DevUiRender for Invulnerability {
+    fn dev_ui_render(&mut self, ui: &imgui::Ui, _world: &specs::World) {
+        use imgui::*;
+
+        ui.text(im_str!("Invulnerability"));
+        ui.input_float(im_str!("seconds after a hit"), &mut self.seconds)
+            .build();
+        ui.text(im_str!("remaining: {:.2}", self.remaining));
+    }
+}
+
+//regeneration component
+#[derive(
+    Default,
+    Component,
+    DevUiComponent,
+    CopyToOtherEntity,
+    AssemblageComponent,
+    Serialize,
+    Deserialize,
+    Clone,
+    Debug,
+)]
+#[storage(HashMapStorage)]
+pub struct Regeneration {
+    pub per_second: f32,
+    // how long after being hurt they have to wait before they start healing.
+    #[serde(default)]
+    pub delay: f32,
+    #[serde(skip)]
+    pub since_hurt: f32,
+}
+impl DevUiRender for Regeneration {
+    fn dev_ui_render(&mut self, ui: &imgui::Ui, _world: &specs::World) {
+        use imgui::*;
+
+        ui.text(im_str!("Regeneration"));
+        ui.input_float(im_str!("per second"), &mut self.per_second)
+            .build();
+        ui.input_float(im_str!("delay after a hit"), &mut self.delay)
+            .build();
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DeathState {
+    Alive,
+    // they just hit 0, the script's been told but nothing else has happened yet.
+    Dying,
+    // everything that happens when they die has happened.
+    Dead,
+}
+impl Default for DeathState {
+    fn default() -> Self {
+        DeathState::Alive
+    }
+}
+
+//on death component
+#[derive(
+    Default,
+    Component,
+    DevUiComponent,
+    CopyToOtherEntity,
+    AssemblageComponent,
+    Serialize,
+    Deserialize,
+    Clone,
+    Debug,
+)]
+#[storage(HashMapStorage)]
+pub struct OnDeath {
+    // called on them once their health hits 0, the payload has the `killer` if there was one.
+    pub script: ScriptEvent,
+    // if they've got an Explodeable, it goes off.
+    #[serde(default)]
+    pub explode: bool,
+    // an assemblage to build where they died, like a corpse.
+    #[serde(default)]
+    pub spawn: Option<String>,
+    #[serde(default)]
+    pub delete_entity: bool,
+    #[serde(skip)]
+    pub state: DeathState,
+    #[serde(skip)]
+    pub killer: Option<u64>,
+}
+impl DevUiRender for OnDeath {
+    fn dev_ui_render(&mut self, ui: &imgui::Ui, world: &specs::World) {
+        use imgui::*;
+
+        ui.text(im_str!("OnDeath"));
+
+        self.script
+            .fn_input_ui(&ui, im_str!("death handler"), world);
+
+        ui.checkbox(im_str!("< explode"), &mut self.explode);
+        ui.checkbox(im_str!("< delete entity"), &mut self.delete_entity);
+
+        let mut spawn = ImString::with_capacity(50);
+        spawn.push_str(self.spawn.as_ref().map_or("", |s| s.as_str()));
+        if ui
+            .input_text(im_str!("< spawn on death"), &mut spawn)
+            .build()
+        {
+            self.spawn = Some(spawn.to_str().to_owned()).filter(|s| !s.is_empty());
+        }
+    }
+}
@@ -0,0 +1,197 @@
+use super::prelude::*;
+use std::collections::BTreeMap;
+
+/// How an Item ends up in someone's Inventory.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pickup {
+    // as soon as something with an Inventory bumps into it.
+    Touch,
+    // when the player interacts with it, so it needs to be Interactable too.
+    Interact,
+}
+impl Default for Pickup {
+    fn default() -> Self {
+        Pickup::Touch
+    }
+}
+
+//item component
+#[derive(
+    Default,
+    Component,
+    DevUiComponent,
+    CopyToOtherEntity,
+    AssemblageComponent,
+    Serialize,
+    Deserialize,
+    Clone,
+    Debug,
+)]
+#[storage(HashMapStorage)]
+pub struct Item {
+    // which of the definitions in items.json this is
+    pub key: String,
+    pub quantity: u32,
+    #[serde(default)]
+    pub pickup: Pickup,
+}
+impl DevUiRender for Item {
+    fn dev_ui_render(&mut self, ui: &imgui::Ui, world: &specs::World) {
+        use imgui::*;
+
+        ui.text(im_str!("Item"));
+
+        let definitions = world.read_resource::<ItemDefinitions>();
+        let mut keys = definitions.0.keys().cloned().collect::<Vec<_>>();
+        keys.sort();
+        let im_keys = keys
+            .iter()
+            .map(|key| ImString::new(key.clone()))
+            .collect::<Vec<_>>();
+        let im_str_keys = im_keys.iter().map(ImStr::new).collect::<Vec<_>>();
+
+        let mut key_index = keys
+            .iter()
+            .position(|key| *key == self.key)
+            .map_or(-1, |index| index as i32);
+        if ui.combo(im_str!("item"), &mut key_index, &im_str_keys, 12) && key_index >= 0 {
+            self.key = keys[key_index as usize].clone();
+        }
+
+        let mut quantity = self.quantity as i32;
+        if ui.input_int(im_str!("quantity"), &mut quantity).build() {
+            self.quantity = quantity.max(1) as u32;
+        }
+
+        let mut on_interact = self.pickup == Pickup::Interact;
+        if ui.checkbox(im_str!("picked up on interact"), &mut on_interact) {
+            self.pickup = if on_interact {
+                Pickup::Interact
+            } else {
+                Pickup::Touch
+            };
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ItemStack {
+    pub key: String,
+    pub quantity: u32,
+}
+
+//inventory component
+#[derive(
+    Component,
+    DevUiComponent,
+    CopyToOtherEntity,
+    AssemblageComponent,
+    Serialize,
+    Deserialize,
+    Clone,
+    Debug,
+)]
+#[storage(HashMapStorage)]
+pub struct Inventory {
+    // empty slots are None, there's only ever as many slots as there are here.
+    pub slots: Vec<Option<ItemStack>>,
+}
+impl Default for Inventory {
+    fn default() -> Self {
+        Self {
+            slots: vec![None; 10],
+        }
+    }
+}
+
+impl Inventory {
+    /// How many of an item are in here, across every slot.
+    pub fn count(&self, key: &str) -> u32 {
+        self.slots
+            .iter()
+            .flatten()
+            .filter(|stack| stack.key == key)
+            .map(|stack| stack.quantity)
+            .sum()
+    }
+
+    /// How many of each item are in here.
+    pub fn totals(&self) -> BTreeMap<String, u32> {
+        let mut totals = BTreeMap::new();
+        for stack in self.slots.iter().flatten() {
+            *totals.entry(stack.key.clone()).or_insert(0) += stack.quantity;
+        }
+        totals
+    }
+
+    /// Fills up the stacks of that item that are already here, then the empty slots.
+    /// Returns how many didn't fit.
+    pub fn give(&mut self, key: &str, mut quantity: u32, definition: &ItemDefinition) -> u32 {
+        for stack in self.slots.iter_mut().flatten().filter(|s| s.key == key) {
+            let added = quantity.min(definition.max_stack.saturating_sub(stack.quantity));
+            stack.quantity += added;
+            quantity -= added;
+        }
+
+        for slot in self.slots.iter_mut().filter(|slot| slot.is_none()) {
+            if quantity == 0 {
+                break;
+            }
+            let added = quantity.min(definition.max_stack);
+            *slot = Some(ItemStack {
+                key: key.to_owned(),
+                quantity: added,
+            });
+            quantity -= added;
+        }
+
+        quantity
+    }
+
+    /// Takes that many of an item out, but only if there are that many in here.
+    /// Returns whether or not they were taken.
+    pub fn take(&mut self, key: &str, mut quantity: u32) -> bool {
+        if self.count(key) < quantity {
+            return false;
+        }
+
+        // from the back, so the first slots stay full the longest
+        for slot in self.slots.iter_mut().rev() {
+            if quantity == 0 {
+                break;
+            }
+            if let Some(stack) = slot.as_mut().filter(|s| s.key == key) {
+                let taken = quantity.min(stack.quantity);
+                stack.quantity -= taken;
+                quantity -= taken;
+                if stack.quantity == 0 {
+                    *slot = None;
+                }
+            }
+        }
+
+        true
+    }
+}
+impl DevUiRender for Inventory {
+    fn dev_ui_render(&mut self, ui: &imgui::Ui, _world: &specs::World) {
+        use imgui::*;
+
+        ui.text(im_str!("Inventory"));
+
+        let mut slot_count = self.slots.len() as i32;
+        if ui.input_int(im_str!("slots"), &mut slot_count).build() {
+            // shrinking it throws away whatever was in the slots at the end.
+            self.slots.resize(slot_count.max(0) as usize, None);
+        }
+
+        for (index, slot) in self.slots.iter().enumerate() {
+            match slot {
+                Some(ItemStack { key, quantity }) => {
+                    ui.text(im_str!("{}: {} x{}", index, key, quantity))
+                }
+                None => ui.text(im_str!("{}: empty", index)),
+            }
+        }
+    }
+}
@@ -1,17 +1,23 @@
 mod prelude;
+pub mod ai;
 pub mod assemblages;
+pub mod combat;
 pub mod controls;
 pub mod effects;
 pub mod health;
+pub mod inventory;
 pub mod physics;
 pub mod rendering;
 pub mod scripting;
 pub mod grouping;
 
+pub use ai::*;
 pub use assemblages::*;
+pub use combat::*;
 pub use controls::*;
 pub use effects::*;
 pub use health::*;
+pub use inventory::*;
 pub use physics::*;
 pub use rendering::*;
 pub use scripting::*;
@@ -31,16 +31,16 @@ impl Default for ApplyForce {
 }
 #[allow(dead_code)]
 impl ApplyForce {
-    pub fn random_2d_vec() -> glm::TVec3<f32> {
+    pub fn random_2d_vec<R: Rng>(rng: &mut R) -> glm::TVec3<f32> {
         use nalgebra::Vector3;
         use std::f32;
 
-        let angle = OsRng::new().unwrap().gen_range(0.0, f32::consts::PI * 2.0);
+        let angle = rng.gen_range(0.0, f32::consts::PI * 2.0);
         Vector3::new(angle.cos(), angle.sin(), 0.0)
     }
-    pub fn random_2d_force_with_magnitude(magnitude: f32) -> Self {
+    pub fn random_2d_force_with_magnitude<R: Rng>(rng: &mut R, magnitude: f32) -> Self {
         Self {
-            vec: Self::random_2d_vec() * magnitude,
+            vec: Self::random_2d_vec(rng) * magnitude,
             ..Self::default()
         }
     }
@@ -143,7 +143,7 @@ impl DevUiComponent for Hitbox {
                         let phys_comp = ps.phys_from_hitbox(&mut hitbox);
                         //add the entity as some user data so that when we find it via raycasting
                         //we can detect it for what it is.
-                        ps.name_as_ent(&phys_comp, Box::new(*ent));
+                        ps.name_as_ent(&phys_comp, *ent, id_of_entity(&world, *ent));
                         //add the new physics body to the world.
                         physes.insert(*ent, phys_comp).unwrap();
                     }
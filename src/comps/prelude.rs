@@ -3,5 +3,7 @@ pub use crate::prelude::*;
 pub use custom_component_macro::*;
 pub use custom_component_macro_derive::*;
 pub use serde::{Deserialize, Serialize};
-pub use specs::{Component, DenseVecStorage, FlaggedStorage, HashMapStorage, VecStorage};
+pub use specs::{
+    Component, DenseVecStorage, FlaggedStorage, HashMapStorage, NullStorage, VecStorage,
+};
 pub use specs_derive::Component;
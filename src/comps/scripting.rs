@@ -16,14 +16,40 @@ use super::prelude::*;
 #[storage(VecStorage)]
 pub struct Interactable {
     pub script: ScriptEvent,
+    // the name of a tree in dialogue.json, the conversation starts when they're interacted with.
+    #[serde(default)]
+    pub dialogue: Option<String>,
 }
 impl DevUiRender for Interactable {
-    fn dev_ui_render(&mut self, ui: &imgui::Ui, _world: &specs::World) {
+    fn dev_ui_render(&mut self, ui: &imgui::Ui, world: &specs::World) {
         use imgui::*;
 
         ui.text(im_str!("Interactable"));
 
-        self.script.fn_input_ui(&ui, im_str!("interact handler"));
+        self.script
+            .fn_input_ui(&ui, im_str!("interact handler"), world);
+
+        // the first option is for not having a dialogue at all.
+        let dialogues = world.read_resource::<Dialogues>();
+        let mut tree_names = dialogues.0.keys().cloned().collect::<Vec<_>>();
+        tree_names.sort();
+        let im_names = std::iter::once(ImString::new("(none)"))
+            .chain(tree_names.iter().map(|name| ImString::new(name.clone())))
+            .collect::<Vec<_>>();
+        let im_str_names = im_names.iter().map(ImStr::new).collect::<Vec<_>>();
+
+        let mut dialogue_index = self
+            .dialogue
+            .as_ref()
+            .and_then(|d| tree_names.iter().position(|name| name == d))
+            .map_or(0, |index| index as i32 + 1);
+
+        if ui.combo(im_str!("dialogue"), &mut dialogue_index, &im_str_names, 12) {
+            self.dialogue = match dialogue_index {
+                0 => None,
+                i => Some(tree_names[i as usize - 1].clone()),
+            };
+        }
     }
 }
 
@@ -43,12 +69,13 @@ pub struct EmitCollideEvent {
     pub script: ScriptEvent,
 }
 impl DevUiRender for EmitCollideEvent {
-    fn dev_ui_render(&mut self, ui: &imgui::Ui, _world: &specs::World) {
+    fn dev_ui_render(&mut self, ui: &imgui::Ui, world: &specs::World) {
         use imgui::*;
 
         ui.text(im_str!("Emit Collide Event"));
 
-        self.script.fn_input_ui(&ui, im_str!("collision handler"));
+        self.script
+            .fn_input_ui(&ui, im_str!("collision handler"), world);
     }
 }
 
@@ -66,36 +93,152 @@ impl DevUiRender for EmitCollideEvent {
 #[storage(HashMapStorage)]
 pub struct ScriptEvent {
     pub function: String,
-    pub payload: u32, //there should really be an enum somewhere for what this could be
-                      //maybe could just use Dyon::Variable or something, maybe Into Dyon::V
+    pub payload: ScriptPayload,
 }
 impl DevUiRender for ScriptEvent {
-    fn dev_ui_render(&mut self, ui: &imgui::Ui, _world: &specs::World) {
+    fn dev_ui_render(&mut self, ui: &imgui::Ui, world: &specs::World) {
         use imgui::*;
 
         ui.text(im_str!("ScriptEvent"));
 
-        self.fn_input_ui(&ui, im_str!("event handler name"));
+        self.fn_input_ui(&ui, im_str!("event handler name"), world);
     }
 }
 impl ScriptEvent {
     #[inline]
-    pub fn fn_input_ui(&mut self, ui: &imgui::Ui, input_name: &imgui::ImStr) {
+    pub fn fn_input_ui(&mut self, ui: &imgui::Ui, input_name: &imgui::ImStr, world: &specs::World) {
         use imgui::*;
 
-        ui.text(im_str!("function name: "));
+        handler_input_ui(ui, input_name, &mut self.function, world);
+
+        // it's edited as json, since that's how it looks in types.json anyway.
+        let mut im_payload = ImString::with_capacity(200);
+        im_payload.push_str(&serde_json::to_string(&self.payload).unwrap());
+        if ui.input_text(im_str!("payload"), &mut im_payload).build() {
+            if let Ok(payload) = serde_json::from_str(im_payload.to_str()) {
+                self.payload = payload;
+            }
+        }
+    }
+
+    /// A copy of this event, with a payload describing what happened.
+    /// Whatever payload this event had to begin with goes along too, as "data".
+    pub fn clone_with_payload(&self, fields: Vec<(&str, ScriptPayload)>) -> Self {
+        let mut object = fields
+            .into_iter()
+            .map(|(key, value)| (key.to_owned(), value))
+            .collect::<std::collections::BTreeMap<_, _>>();
+        if self.payload != ScriptPayload::None {
+            object.insert("data".to_owned(), self.payload.clone());
+        }
+
+        Self {
+            function: self.function.clone(),
+            payload: ScriptPayload::Object(object),
+        }
+    }
+}
+
+/// The extra information that's passed to a script handler along with the entity.
+/// Scripts see these as plain Dyon values, entities are just their ids.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum ScriptPayload {
+    None,
+    Number(f64),
+    Text(String),
+    Vector([f32; 4]),
+    Entity(u64),
+    List(Vec<ScriptPayload>),
+    Object(std::collections::BTreeMap<String, ScriptPayload>),
+}
+impl Default for ScriptPayload {
+    fn default() -> Self {
+        ScriptPayload::None
+    }
+}
+impl ScriptPayload {
+    /// Points are handed to scripts as vec4s, since that's what Dyon has.
+    pub fn point(point: &glm::TVec3<f32>) -> Self {
+        ScriptPayload::Vector([point.x, point.y, point.z, 0.0])
+    }
+}
+
+// lets you type in the name of a script handler, or pick one that's known to exist.
+fn handler_input_ui(
+    ui: &imgui::Ui,
+    input_name: &imgui::ImStr,
+    function: &mut String,
+    world: &specs::World,
+) {
+    use imgui::*;
+    let handlers = world.read_resource::<ScriptHandlers>();
 
-        let mut im_function = ImString::with_capacity(100); //self.message.len() + 1);
-        im_function.push_str(&self.function);
-        if ui.input_text(input_name, &mut im_function).build() {
-            self.function = im_function.to_str().to_owned();
+    ui.text(im_str!("function name: "));
+
+    // it can still be typed in, for handlers that haven't been written yet.
+    let mut im_function = ImString::with_capacity(100);
+    im_function.push_str(function);
+    if ui.input_text(input_name, &mut im_function).build() {
+        *function = im_function.to_str().to_owned();
+    }
+
+    let handler_names = handlers
+        .0
+        .iter()
+        .map(|x| ImString::new(x.clone()))
+        .collect::<Vec<_>>();
+    let handler_im_str_names = handler_names.iter().map(ImStr::new).collect::<Vec<_>>();
+
+    let mut handler_index = match handlers.0.iter().position(|x| *x == *function) {
+        Some(index) => index as i32,
+        None => {
+            ui.text(im_str!("that isn't one of the known handlers."));
+            -1
         }
+    };
+
+    if ui.combo(
+        im_str!("known handlers"),
+        &mut handler_index,
+        &handler_im_str_names,
+        12,
+    ) && handler_index >= 0
+    {
+        *function = handlers.0[handler_index as usize].clone();
     }
+}
+
+#[derive(
+    Default,
+    Component,
+    DevUiComponent,
+    CopyToOtherEntity,
+    AssemblageComponent,
+    Serialize,
+    Deserialize,
+    Clone,
+    Debug,
+)]
+#[storage(VecStorage)]
+pub struct ScriptTick {
+    pub function: String,
+    // how long to wait between calls, 0 means every tick.
+    pub interval_ms: f32,
+    // how long it's been since the function was last called.
+    #[serde(skip)]
+    pub elapsed: f32,
+}
+impl DevUiRender for ScriptTick {
+    fn dev_ui_render(&mut self, ui: &imgui::Ui, world: &specs::World) {
+        use imgui::*;
+
+        ui.text(im_str!("Script Tick"));
+
+        handler_input_ui(&ui, im_str!("tick handler"), &mut self.function, world);
 
-    pub fn clone_with_payload(&self, pld: u32) -> Self {
-        let mut clone = self.clone();
-        clone.payload = pld.clone();
-        clone
+        ui.input_float(im_str!("interval (ms)"), &mut self.interval_ms)
+            .step(100.0)
+            .build();
     }
 }
 
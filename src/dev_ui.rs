@@ -168,6 +168,94 @@ fn type_editor(ui: &Ui, world: &specs::World) {
     }
 }
 
+#[inline]
+fn dyon_console(ui: &Ui, world: &specs::World) {
+    {
+        let dyon_console = &world.read_resource::<DyonConsole>().0;
+        for message in dyon_console.split('\n') {
+            console_line(ui, world, message);
+        }
+    }
+
+    // evaluates Dyon against the world, i.e. `quest_status("melon delivery")`
+    ui.separator();
+    let mut repl = world.write_resource::<DyonRepl>();
+    if ui
+        .input_text(im_str!("dyon"), &mut repl.input)
+        .enter_returns_true(true)
+        .build()
+    {
+        repl.submit();
+    }
+
+    // up and down go through what's been run before, like in a terminal
+    if ui.is_item_active() {
+        let imgui = ui.imgui();
+        if imgui.is_key_pressed(imgui.get_key_index(ImGuiKey::UpArrow)) {
+            repl.previous();
+        } else if imgui.is_key_pressed(imgui.get_key_index(ImGuiKey::DownArrow)) {
+            repl.next();
+        }
+    }
+}
+
+// entity ids like #12 in the console are buttons that select that entity.
+fn console_line(ui: &Ui, world: &specs::World, line: &str) {
+    let mut segments: Vec<(&str, Option<u64>)> = Vec::new();
+    let mut rest = line;
+    while let Some(start) = rest.find('#') {
+        let digits = rest[start + 1..]
+            .bytes()
+            .take_while(u8::is_ascii_digit)
+            .count();
+        let end = start + 1 + digits;
+
+        match rest[start + 1..end].parse() {
+            Ok(id) => {
+                segments.push((&rest[..start], None));
+                segments.push((&rest[start..end], Some(id)));
+            }
+            // just a # on its own
+            Err(_) => segments.push((&rest[..end], None)),
+        }
+        rest = &rest[end..];
+    }
+    segments.push((rest, None));
+    segments.retain(|(text, _)| !text.is_empty());
+
+    if segments.is_empty() {
+        ui.text(im_str!(""));
+    }
+
+    for (index, (text, id)) in segments.into_iter().enumerate() {
+        if index != 0 {
+            ui.same_line(0.0);
+        }
+
+        match id {
+            Some(id) => {
+                if ui.small_button(im_str!("{}", text)) {
+                    choose_ent_by_id(world, id);
+                }
+            }
+            None => ui.text(im_str!("{}", text)),
+        }
+    }
+}
+
+fn choose_ent_by_id(world: &specs::World, id: u64) {
+    if let Some(ent) = entity_from_id(world, id) {
+        // the entity editor only knows what to do with things that were built from a type
+        if world.read_storage::<Assemblaged>().get(ent).is_some() {
+            world.write_resource::<Compendium>().choose_ent(
+                ent,
+                &world.entities(),
+                &mut world.write_storage::<BoxOutline>(),
+            );
+        }
+    }
+}
+
 #[inline]
 fn add_type_popup(ui: &Ui, world: &specs::World) {
     let mut compium = world.write_resource::<Compendium>();
@@ -346,6 +434,148 @@ fn compendium(ui: &Ui, world: &specs::World) {
     ));
 }
 
+#[inline]
+fn input_bindings(ui: &Ui, world: &specs::World) {
+    let mut bindings = world.write_resource::<InputBindings>();
+    let ls = world.read_resource::<LocalState>();
+
+    // if we're waiting on a new chord, take whatever they're holding down
+    // once they've pressed something (that isn't just them clicking around the dev ui)
+    if let Some(action) = bindings.rebinding.clone() {
+        let pressed_something = ls.tapped_buttons.iter().any(|button| match button {
+            InputButton::Mouse(_) => !ui.want_capture_mouse(),
+            _ => true,
+        });
+
+        if pressed_something {
+            let input = &ls.last_input;
+            let mut chord: Chord = input
+                .keys_held
+                .iter()
+                .map(|key| InputButton::Key(*key))
+                .chain(
+                    input
+                        .mouse_buttons_held
+                        .iter()
+                        .map(|button| InputButton::Mouse(*button)),
+                )
+                .chain(
+                    input
+                        .gamepad
+                        .buttons_held
+                        .iter()
+                        .map(|button| InputButton::Gamepad(*button)),
+                )
+                .collect();
+            chord.sort_by_key(InputButton::name);
+
+            bindings.actions.entry(action).or_default().push(chord);
+            bindings.rebinding = None;
+        }
+    }
+
+    let mut new_rebinding = None;
+    for (action, chords) in bindings.actions.iter_mut() {
+        ui.text(im_str!("{}", action));
+        ui.same_line(125.0);
+        ui.text(im_str!(
+            "{}",
+            chords
+                .iter()
+                .map(InputBindings::chord_name)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+
+        if ui.small_button(im_str!("Add##{}", action)) {
+            new_rebinding = Some(action.clone());
+        }
+        ui.same_line(0.0);
+        if ui.small_button(im_str!("Rebind##{}", action)) {
+            chords.clear();
+            new_rebinding = Some(action.clone());
+        }
+        ui.same_line(0.0);
+        if ui.small_button(im_str!("Clear##{}", action)) {
+            chords.clear();
+        }
+    }
+    if new_rebinding.is_some() {
+        bindings.rebinding = new_rebinding;
+    }
+
+    ui.separator();
+
+    if let Some(action) = bindings.rebinding.clone() {
+        ui.text(im_str!("Press the new keys for {}...", action));
+        if ui.button(im_str!("Cancel"), (0.0, 0.0)) {
+            bindings.rebinding = None;
+        }
+    }
+
+    if ui.button(im_str!("Save Bindings"), (0.0, 0.0)) {
+        if let Err(e) = bindings.save() {
+            let mut dyon_console = world.write_resource::<DyonConsole>();
+            dyon_console
+                .0
+                .push_str(&format!("Couldn't save the input bindings: {}\n", e));
+        }
+    }
+}
+
+#[inline]
+fn save_slots(ui: &Ui, world: &specs::World) {
+    let mut asmblgr = world.write_resource::<Assemblager>();
+    let lu = world.read_resource::<LazyUpdate>();
+
+    ui.text_wrapped(im_str!(
+        "Loading a slot throws away anything that hasn't been saved, \
+         and saving goes to whichever slot was loaded last."
+    ));
+    ui.separator();
+
+    // None is the starting world, instances.json.
+    let slots = std::iter::once(None).chain(list_slots().into_iter().map(Some));
+    for slot in slots {
+        let label = ImString::new(slot.as_ref().map_or("(starting world)", |s| s.as_str()));
+        if ui.selectable(
+            &label,
+            asmblgr.slot == slot,
+            ImGuiSelectableFlags::empty(),
+            ImVec2::new(0.0, 0.0),
+        ) && asmblgr.slot != slot
+        {
+            asmblgr.slot = slot;
+            let instances_path = asmblgr.instances_path();
+            lu.exec_mut(move |world| {
+                let mut errors = LoadErrors::default();
+                reload_instances(world, instances_path, &mut errors);
+                world.write_resource::<LoadErrors>().0.extend(errors.0);
+            });
+        }
+    }
+}
+
+#[inline]
+fn load_errors(ui: &Ui, world: &specs::World) {
+    let mut load_errors = world.write_resource::<LoadErrors>();
+
+    ui.text_wrapped(im_str!(
+        "Some content couldn't be loaded, so it was skipped. \
+         Saving now will leave it out of the save, so fix these first!"
+    ));
+    ui.separator();
+
+    for error in load_errors.0.iter() {
+        ui.text_wrapped(im_str!("{}", error));
+    }
+
+    ui.separator();
+    if ui.button(im_str!("Dismiss"), (0.0, 0.0)) {
+        load_errors.0.clear();
+    }
+}
+
 pub struct DevUiState {
     frame_size: imgui::FrameSize,
     pub imgui: ImGui,
@@ -448,6 +678,31 @@ impl DevUiState {
                 });
         });
 
+        // only shows up if something's gone wrong
+        if !world.read_resource::<LoadErrors>().0.is_empty() {
+            ui.window(im_str!("Load Errors"))
+                .position(
+                    (1366.0 / 2.0 - 250.0, 768.0 / 2.0 - 150.0),
+                    ImGuiCond::FirstUseEver,
+                )
+                .size((500.0, 300.0), ImGuiCond::FirstUseEver)
+                .build(|| load_errors(&ui, &world));
+        }
+
+        // so you can change which keys do what
+        ui.window(im_str!("Input Bindings"))
+            .position((250.0, 0.0), ImGuiCond::FirstUseEver)
+            .size((350.0, 250.0), ImGuiCond::FirstUseEver)
+            .collapsed(true, ImGuiCond::FirstUseEver)
+            .build(|| input_bindings(&ui, &world));
+
+        // so you can hop between saves without restarting
+        ui.window(im_str!("Save Slots"))
+            .position((600.0, 0.0), ImGuiCond::FirstUseEver)
+            .size((250.0, 200.0), ImGuiCond::FirstUseEver)
+            .collapsed(true, ImGuiCond::FirstUseEver)
+            .build(|| save_slots(&ui, &world));
+
         // show the little window with the FPS in it
         ui.show_metrics_window(&mut true);
 
@@ -461,12 +716,7 @@ impl DevUiState {
                 ui.window(im_str!("Dyon Console"))
                     .position((0.0, 768.0 - 270.0), ImGuiCond::FirstUseEver)
                     .size((475.0, 270.0), ImGuiCond::FirstUseEver)
-                    .build(|| {
-                        let dyon_console = &world.read_resource::<DyonConsole>().0;
-                        for message in dyon_console.split('\n') {
-                            ui.text(im_str!("{}", message));
-                        }
-                    });
+                    .build(|| dyon_console(&ui, &world));
             },
         );
 
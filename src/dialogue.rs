@@ -0,0 +1,483 @@
+use crate::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+
+pub const DIALOGUE_PATH: &str = "./src/data/dialogue.json";
+
+fn default_is() -> Value {
+    Value::Bool(true)
+}
+
+/// Decides whether parts of a conversation are available.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum DialogueCondition {
+    // checks a value in the Dyon cache of whoever's talking,
+    // so scripts can steer conversations just by writing to their caches.
+    // the value defaults to true.
+    Cache {
+        cache: String,
+        #[serde(default = "default_is")]
+        is: Value,
+    },
+    // checks how far the player's gotten in a quest, and which stage they're on if there's one.
+    Quest {
+        quest: String,
+        is: QuestStatus,
+        #[serde(default)]
+        stage: Option<usize>,
+    },
+}
+
+/// What conditions get checked against.
+pub struct DialogueFacts<'a> {
+    pub dyon_data: &'a DyonData,
+    pub quest_log: &'a QuestLog,
+}
+
+impl DialogueCondition {
+    fn holds(&self, speaker_id: u64, facts: &DialogueFacts) -> bool {
+        match self {
+            // things that haven't been put in the cache yet count as false,
+            // that way `"is": false` works before a script has ever touched the cache.
+            DialogueCondition::Cache { cache, is } => same_value(
+                &facts
+                    .dyon_data
+                    .cache_value(speaker_id, cache)
+                    .unwrap_or(Value::Bool(false)),
+                is,
+            ),
+            DialogueCondition::Quest { quest, is, stage } => {
+                facts.quest_log.status(quest) == *is
+                    && stage.map_or(true, |stage| facts.quest_log.stage(quest) == Some(stage))
+            }
+        }
+    }
+}
+
+// every number in Dyon is a float, so a cache value of 2.0 has to match `"is": 2`.
+fn same_value(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Number(a), Value::Number(b)) => a.as_f64() == b.as_f64(),
+        (Value::Array(a), Value::Array(b)) => {
+            a.len() == b.len() && a.iter().zip(b).all(|(a, b)| same_value(a, b))
+        }
+        (Value::Object(a), Value::Object(b)) => {
+            a.len() == b.len()
+                && a.iter()
+                    .all(|(key, a)| b.get(key).map_or(false, |b| same_value(a, b)))
+        }
+        _ => a == b,
+    }
+}
+
+fn all_hold(conditions: &[DialogueCondition], speaker_id: u64, facts: &DialogueFacts) -> bool {
+    conditions.iter().all(|c| c.holds(speaker_id, facts))
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct DialogueChoice {
+    pub text: String,
+    // the node to go to next, if there isn't one the conversation is over.
+    #[serde(default)]
+    pub goto: Option<String>,
+    // a script handler that's called on the speaker when this is chosen.
+    #[serde(default)]
+    pub event: Option<String>,
+    // the choice is only offered if these all hold.
+    #[serde(default)]
+    pub conditions: Vec<DialogueCondition>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct DialogueNode {
+    pub speaker: String,
+    pub lines: Vec<String>,
+    #[serde(default)]
+    pub choices: Vec<DialogueChoice>,
+    // where to go after the last line if there aren't any choices.
+    #[serde(default)]
+    pub next: Option<String>,
+}
+
+/// Where a conversation can start. The first entry whose conditions all hold is used.
+#[derive(Deserialize, Debug, Clone)]
+pub struct DialogueEntry {
+    pub node: String,
+    #[serde(default)]
+    pub conditions: Vec<DialogueCondition>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct DialogueTree {
+    pub entries: Vec<DialogueEntry>,
+    pub nodes: HashMap<String, DialogueNode>,
+}
+
+impl DialogueTree {
+    // every node name mentioned anywhere in the tree, and where it was mentioned.
+    fn links(&self) -> Vec<(String, &str)> {
+        let entries = self
+            .entries
+            .iter()
+            .enumerate()
+            .map(|(i, entry)| (format!("entries[{}]", i), entry.node.as_str()));
+
+        let nodes = self.nodes.iter().flat_map(|(name, node)| {
+            let next = node
+                .next
+                .iter()
+                .map(move |next| (format!("nodes.{}.next", name), next.as_str()));
+            let choices = node.choices.iter().enumerate().filter_map(move |(i, c)| {
+                let goto = c.goto.as_ref()?;
+                Some((format!("nodes.{}.choices[{}]", name, i), goto.as_str()))
+            });
+            next.chain(choices)
+        });
+
+        entries.chain(nodes).collect()
+    }
+}
+
+/// All of the conversations NPCs can have, keyed by name.
+/// Interactables with a dialogue start one of these instead of just calling their script.
+#[derive(Default, Debug, Clone)]
+pub struct Dialogues(pub HashMap<String, DialogueTree>);
+
+impl Dialogues {
+    /// Trees that mention nodes they don't have are still loaded,
+    /// but the broken links are reported in `errors`.
+    pub fn load(errors: &mut LoadErrors) -> Self {
+        let trees: HashMap<String, DialogueTree> = read_content_file(DIALOGUE_PATH, errors)
+            .and_then(|data| {
+                serde_json::from_str(&data)
+                    .map_err(|e| errors.push(LoadError::from_json_error(DIALOGUE_PATH, e)))
+                    .ok()
+            })
+            .unwrap_or_default();
+
+        for (tree_name, tree) in trees.iter() {
+            for (location, node) in tree.links() {
+                if !tree.nodes.contains_key(node) {
+                    errors.push(LoadError::new(
+                        DIALOGUE_PATH,
+                        format!("{}.{}", tree_name, location),
+                        format!("There's no node called {:?} in this tree.", node),
+                    ));
+                }
+            }
+        }
+
+        Dialogues(trees)
+    }
+
+    pub fn node(&self, tree: &str, node: &str) -> Option<&DialogueNode> {
+        self.0.get(tree)?.nodes.get(node)
+    }
+}
+
+/// The conversation that's going on right now.
+#[derive(Debug, Clone)]
+pub struct ActiveDialogue {
+    pub tree: String,
+    pub node: String,
+    // which of the node's lines is being shown
+    pub line: usize,
+    // which of the choices that are offered is highlighted, for keyboards and gamepads.
+    pub selected: usize,
+    pub speaker: specs::Entity,
+    // persistent ids, for the conditions and the payloads of the events.
+    pub speaker_id: u64,
+    pub player_id: u64,
+}
+
+/// Something clicked in the dialogue window. It goes into the next frame's UserInput, so it's
+/// applied during a tick like any other button, and replays click the same things.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum DialogueClick {
+    Advance,
+    // by the choice's index in the node's choices.
+    Choose(usize),
+}
+
+/// Only one conversation can happen at a time, and the player can't walk away from it.
+#[derive(Default, Debug, Clone)]
+pub struct DialogueState {
+    pub active: Option<ActiveDialogue>,
+    // what was clicked in the dialogue window this frame, if anything.
+    pub clicked: Option<DialogueClick>,
+}
+
+impl DialogueState {
+    pub fn is_active(&self) -> bool {
+        self.active.is_some()
+    }
+
+    /// There's no one to talk to once the speaker's been deleted, by a script, a hot reload or
+    /// anything else, and nothing to say if the node was taken out of dialogue.json or has no
+    /// lines, so the conversation just ends.
+    pub fn end_if_gone(&mut self, ents: &specs::world::EntitiesRes, dialogues: &Dialogues) {
+        let speaker_gone = self
+            .active
+            .as_ref()
+            .map_or(false, |active| !ents.is_alive(active.speaker));
+        if speaker_gone || self.line(dialogues).is_none() {
+            self.active = None;
+        }
+    }
+
+    /// Starts the first entry of `tree` whose conditions hold.
+    /// Returns whether or not a conversation was started.
+    pub fn start(
+        &mut self,
+        tree: &str,
+        (speaker, speaker_id): (specs::Entity, u64),
+        player_id: u64,
+        dialogues: &Dialogues,
+        facts: &DialogueFacts,
+    ) -> bool {
+        let entry = dialogues.0.get(tree).and_then(|t| {
+            t.entries
+                .iter()
+                .find(|entry| all_hold(&entry.conditions, speaker_id, facts))
+        });
+
+        match entry {
+            Some(entry) => {
+                self.active = Some(ActiveDialogue {
+                    tree: tree.to_owned(),
+                    node: entry.node.clone(),
+                    line: 0,
+                    selected: 0,
+                    speaker,
+                    speaker_id,
+                    player_id,
+                });
+                true
+            }
+            None => {
+                warn!("Dialogue tree {:?} has no entry that can be started.", tree);
+                false
+            }
+        }
+    }
+
+    pub fn node<'a>(&self, dialogues: &'a Dialogues) -> Option<&'a DialogueNode> {
+        let active = self.active.as_ref()?;
+        dialogues.node(&active.tree, &active.node)
+    }
+
+    pub fn line<'a>(&self, dialogues: &'a Dialogues) -> Option<&'a str> {
+        let line = self.active.as_ref()?.line;
+        self.node(dialogues)?.lines.get(line).map(|l| l.as_str())
+    }
+
+    /// Choices are only offered once the last line has been shown.
+    pub fn on_last_line(&self, dialogues: &Dialogues) -> bool {
+        match (&self.active, self.node(dialogues)) {
+            (Some(active), Some(node)) => active.line + 1 >= node.lines.len(),
+            _ => true,
+        }
+    }
+
+    /// The choices whose conditions hold, along with their index in the node's choices.
+    pub fn choices<'a>(
+        &self,
+        dialogues: &'a Dialogues,
+        facts: &DialogueFacts,
+    ) -> Vec<(usize, &'a DialogueChoice)> {
+        match (&self.active, self.node(dialogues)) {
+            (Some(active), Some(node)) if self.on_last_line(dialogues) => node
+                .choices
+                .iter()
+                .enumerate()
+                .filter(|(_, c)| all_hold(&c.conditions, active.speaker_id, facts))
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Moves the highlight up or down the offered choices.
+    pub fn select(&mut self, delta: i32, dialogues: &Dialogues, facts: &DialogueFacts) {
+        let count = self.choices(dialogues, facts).len() as i32;
+        match self.active.as_mut() {
+            Some(active) if count > 0 => {
+                active.selected = (active.selected as i32 + delta).max(0).min(count - 1) as usize;
+            }
+            _ => {}
+        }
+    }
+
+    fn goto(&mut self, node: Option<&String>) {
+        match (node, self.active.as_mut()) {
+            (Some(node), Some(active)) => {
+                active.node = node.clone();
+                active.line = 0;
+                active.selected = 0;
+            }
+            _ => self.active = None,
+        }
+    }
+
+    /// What the interact button does while talking: shows the next line,
+    /// picks the highlighted choice, or ends the conversation if there's nothing left.
+    pub fn advance(
+        &mut self,
+        dialogues: &Dialogues,
+        facts: &DialogueFacts,
+    ) -> Option<(u64, ScriptEvent)> {
+        if !self.on_last_line(dialogues) {
+            if let Some(active) = self.active.as_mut() {
+                active.line += 1;
+            }
+            return None;
+        }
+
+        let choices = self.choices(dialogues, facts);
+        if choices.is_empty() {
+            let next = self.node(dialogues).and_then(|n| n.next.clone());
+            self.goto(next.as_ref());
+            return None;
+        }
+
+        let selected = self.active.as_ref()?.selected.min(choices.len() - 1);
+        let (index, _) = choices[selected];
+        self.choose(index, dialogues)
+    }
+
+    /// Picks a choice by its index in the node's choices. If the choice has an event,
+    /// it's returned along with the id of the entity it's for.
+    pub fn choose(&mut self, index: usize, dialogues: &Dialogues) -> Option<(u64, ScriptEvent)> {
+        let choice = self.node(dialogues)?.choices.get(index)?.clone();
+        let active = self.active.as_ref()?;
+
+        let event = choice.event.as_ref().map(|function| {
+            (
+                active.speaker_id,
+                ScriptEvent {
+                    function: function.clone(),
+                    payload: ScriptPayload::Object(
+                        vec![
+                            ("player".to_owned(), ScriptPayload::Entity(active.player_id)),
+                            (
+                                "choice".to_owned(),
+                                ScriptPayload::Text(choice.text.clone()),
+                            ),
+                        ]
+                        .into_iter()
+                        .collect(),
+                    ),
+                },
+            )
+        });
+
+        self.goto(choice.goto.as_ref());
+        event
+    }
+}
+
+/// Draws the conversation that's going on, if there is one, at the bottom of the screen.
+/// Choices can be clicked, or picked with the same buttons used to walk around.
+pub fn dialogue_ui(ctx: &mut nuklear::Context, world: &specs::World, width: f32, height: f32) {
+    use nuklear::{Flags, PanelFlags, Rect};
+
+    let dialogues = world.read_resource::<Dialogues>();
+    let dyon_data = world.read_resource::<DyonData>();
+    let quest_log = world.read_resource::<QuestLog>();
+    let facts = DialogueFacts {
+        dyon_data: &dyon_data,
+        quest_log: &quest_log,
+    };
+    let mut state = world.write_resource::<DialogueState>();
+
+    // the Interact system ends conversations that can't go on anymore.
+    let (speaker, line) = match (state.node(&dialogues), state.line(&dialogues)) {
+        (Some(node), Some(line)) => (node.speaker.clone(), line.to_owned()),
+        _ => return,
+    };
+    let choices = state.choices(&dialogues, &facts);
+    let selected = state.active.as_ref().map_or(0, |a| a.selected);
+
+    let bounds = Rect {
+        x: width * 0.15,
+        y: height * 0.65,
+        w: width * 0.7,
+        h: height * 0.3,
+    };
+    let flags = PanelFlags::Border as Flags | PanelFlags::Title as Flags;
+
+    let mut clicked = None;
+    if ctx.begin(speaker.as_str().into(), bounds, flags) {
+        ctx.layout_row_dynamic(40.0, 1);
+        ctx.text_wrap(&line);
+
+        ctx.layout_row_dynamic(24.0, 1);
+        if choices.is_empty() {
+            if ctx.button_text("...") {
+                clicked = Some(DialogueClick::Advance);
+            }
+        } else {
+            for (i, (index, choice)) in choices.iter().enumerate() {
+                let marker = if i == selected { ">" } else { " " };
+                if ctx.button_text(&format!("{} {}", marker, choice.text)) {
+                    clicked = Some(DialogueClick::Choose(*index));
+                }
+            }
+        }
+    }
+    ctx.end();
+
+    // it's only applied once a tick comes around, rendering doesn't change the world.
+    if clicked.is_some() {
+        state.clicked = clicked;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use specs::Join;
+
+    #[test]
+    fn numeric_cache_conditions() {
+        let mut headless = Headless::new();
+        let id = headless
+            .world
+            .read_storage::<U64Marker>()
+            .join()
+            .next()
+            .unwrap()
+            .id();
+
+        headless
+            .eval(&format!(
+                "fn main() {{
+                    cache := get_cache_entity({})
+                    cache.visits := 2
+                    cache.seen := [1, {{times: 3}}]
+                }}",
+                id
+            ))
+            .unwrap();
+
+        let dyon_data = headless.world.read_resource::<DyonData>();
+        let quest_log = headless.world.read_resource::<QuestLog>();
+        let facts = DialogueFacts {
+            dyon_data: &dyon_data,
+            quest_log: &quest_log,
+        };
+        let holds = |condition: Value| {
+            serde_json::from_value::<DialogueCondition>(condition)
+                .unwrap()
+                .holds(id, &facts)
+        };
+
+        assert!(holds(json!({ "cache": "visits", "is": 2 })));
+        assert!(holds(json!({ "cache": "visits", "is": 2.0 })));
+        assert!(!holds(json!({ "cache": "visits", "is": 3 })));
+        assert!(holds(json!({ "cache": "seen", "is": [1, { "times": 3 }] })));
+        assert!(holds(json!({ "cache": "unset", "is": false })));
+    }
+}
@@ -1,16 +1,193 @@
 use current::CurrentGuard;
 use dyon::Variable;
+use log::warn;
+use serde_json::Value;
+use std::cell::Cell;
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 extern crate serde;
 
 // DyonData lets scripts allocate their own objects on the fly
 // and these can even be saved for when the game is closed!
+// it's a resource so that the Assemblager can get at it while saving.
+// both maps are keyed by the entities' persistent ids.
 #[derive(Default)]
-struct DyonData {
-    cache_map: HashMap<specs::world::Index, dyon::Object>,
-    cache_refs: HashMap<specs::world::Index, usize>,
+pub struct DyonData {
+    cache_map: HashMap<u64, dyon::Object>,
+    cache_refs: HashMap<u64, usize>,
+}
+
+impl DyonData {
+    /// Turns the caches of every entity that `is_saved` into json.
+    /// There's no point keeping the caches of entities that won't be around after a load.
+    pub fn save_caches<F: Fn(u64) -> bool>(&self, is_saved: F) -> HashMap<u64, Value> {
+        self.cache_map
+            .iter()
+            .filter(|(id, _)| is_saved(**id))
+            .filter_map(|(id, cache)| match object_to_json(cache) {
+                Ok(json) => Some((*id, json)),
+                Err(e) => {
+                    warn!("Couldn't save the Dyon cache for entity #{}: {}", id, e);
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// One of the values in an entity's cache as json, for things outside of scripts
+    /// that want to know what scripts have been up to, like dialogue conditions.
+    pub fn cache_value(&self, id: u64, key: &str) -> Option<Value> {
+        let value = self.cache_map.get(&id)?.get(&Arc::new(key.to_owned()))?;
+        variable_to_json(value).ok()
+    }
+
+    pub fn remove_cache(&mut self, id: u64) {
+        self.cache_map.remove(&id);
+        self.cache_refs.remove(&id);
+    }
+
+    /// Throws out the caches of every entity that isn't `alive` anymore.
+    pub fn retain_caches<F: Fn(u64) -> bool>(&mut self, alive: F) {
+        self.cache_map.retain(|id, _| alive(*id));
+        self.cache_refs.retain(|id, _| alive(*id));
+    }
+
+    /// Replaces all of the caches with the ones from a save.
+    pub fn load_caches(&mut self, caches: HashMap<u64, Value>) {
+        self.cache_map.clear();
+        self.cache_refs.clear();
+
+        for (id, json) in caches {
+            match json_to_variable(&json) {
+                Ok(Variable::Object(cache)) => {
+                    self.cache_map.insert(id, cache);
+                }
+                Ok(_) => warn!("Dyon cache for entity #{} isn't an object.", id),
+                Err(e) => warn!("Couldn't load the Dyon cache for entity #{}: {}", id, e),
+            }
+        }
+    }
+}
+
+fn object_to_json(object: &dyon::Object) -> Result<Value, String> {
+    object
+        .iter()
+        .map(|(key, value)| Ok((key.to_string(), variable_to_json(value)?)))
+        .collect::<Result<serde_json::Map<_, _>, String>>()
+        .map(Value::Object)
+}
+
+fn variable_to_json(variable: &Variable) -> Result<Value, String> {
+    use serde_json::json;
+
+    Ok(match variable {
+        Variable::Text(text) => json!(**text),
+        Variable::F64(number, _) => json!(number),
+        Variable::Bool(boolean, _) => json!(boolean),
+        // json doesn't have vec4s, so they're wrapped up to keep them from coming back as arrays.
+        Variable::Vec4(vec) => json!({ "vec4": vec }),
+        Variable::Object(object) => object_to_json(object)?,
+        Variable::Array(array) => Value::Array(
+            array
+                .iter()
+                .map(variable_to_json)
+                .collect::<Result<_, _>>()?,
+        ),
+        // the stack can have references to other variables on it,
+        // but what they're pointing at is what's actually worth saving.
+        Variable::Ref(_) => return Err("Can't save a reference.".to_owned()),
+        _ => return Err("The game can't save a variable of that type!".to_owned()),
+    })
+}
+
+// the library functions that give back entities, or arrays of them.
+const ENTITY_FUNCTIONS: [&str; 2] = ["all_with_id", "spawn_at_entity"];
+
+// whether a line typed into the REPL comes out to entities, i.e. `all_with_id("melo")[0]`.
+fn returns_entities(line: &str) -> bool {
+    let line = line.trim_start();
+    ENTITY_FUNCTIONS.iter().any(|function| {
+        line.starts_with(function) && line[function.len()..].trim_start().starts_with('(')
+    })
+}
+
+// how a value shows up in the REPL. if it's `entities`, the ids of ones that are around are
+// shown like #12, so the console can turn them into buttons that select it.
+// other numbers are just numbers, even if they happen to match an entity's id.
+fn describe_variable(variable: &Variable, world: &specs::World, entities: bool) -> String {
+    match variable {
+        Variable::F64(number, _)
+            if entities
+                && number.fract() == 0.0
+                && *number >= 0.0
+                && crate::prelude::entity_from_id(world, *number as u64).is_some() =>
+        {
+            format!("#{}", number)
+        }
+        Variable::Array(array) => format!(
+            "[{}]",
+            array
+                .iter()
+                .map(|x| describe_variable(x, world, entities))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        Variable::Option(None) => "none()".to_owned(),
+        Variable::Option(Some(x)) => {
+            format!("some({})", describe_variable(x, world, entities))
+        }
+        other => variable_to_json(other)
+            .map(|json| json.to_string())
+            .unwrap_or_else(|e| e),
+    }
+}
+
+/// What a script handler gets as its second argument.
+fn payload_to_variable(payload: &crate::prelude::ScriptPayload) -> Variable {
+    use crate::prelude::ScriptPayload::*;
+
+    match payload {
+        None => Variable::Option(Option::None),
+        Number(number) => Variable::F64(*number, Option::None),
+        Text(text) => Variable::Text(Arc::new(text.clone())),
+        Vector(vec) => Variable::Vec4(*vec),
+        Entity(id) => Variable::F64(*id as f64, Option::None),
+        List(list) => Variable::Array(Arc::new(list.iter().map(payload_to_variable).collect())),
+        Object(object) => Variable::Object(Arc::new(
+            object
+                .iter()
+                .map(|(key, value)| (Arc::new(key.clone()), payload_to_variable(value)))
+                .collect(),
+        )),
+    }
+}
+
+fn json_to_variable(json: &Value) -> Result<Variable, String> {
+    Ok(match json {
+        Value::String(text) => Variable::Text(Arc::new(text.clone())),
+        Value::Number(number) => Variable::F64(number.as_f64().ok_or("Bad number.")?, None),
+        Value::Bool(boolean) => Variable::Bool(*boolean, None),
+        Value::Array(array) => Variable::Array(Arc::new(
+            array
+                .iter()
+                .map(json_to_variable)
+                .collect::<Result<_, _>>()?,
+        )),
+        Value::Object(object) => match object.get("vec4") {
+            Some(vec) if object.len() == 1 => {
+                Variable::Vec4(serde_json::from_value(vec.clone()).map_err(|e| e.to_string())?)
+            }
+            _ => Variable::Object(Arc::new(
+                object
+                    .iter()
+                    .map(|(key, value)| Ok((Arc::new(key.clone()), json_to_variable(value)?)))
+                    .collect::<Result<_, String>>()?,
+            )),
+        },
+        Value::Null => return Err("Dyon doesn't have null.".to_owned()),
+    })
 }
 
 // this struct is exposed as a resource, it stores the data that's
@@ -18,14 +195,54 @@ struct DyonData {
 #[derive(Default)]
 pub struct DyonConsole(pub String);
 
+// one handler that's going to be run this tick,
+// with the id of the entity it's for and whatever else it gets told.
+struct ScriptCall {
+    function: String,
+    id: u64,
+    arg: Variable,
+}
+
+// how long a single handler gets to run before it's stopped.
+const HANDLER_BUDGET: Duration = Duration::from_millis(50);
+// how many times in a row a handler can fail before it's not run anymore.
+pub const MAX_HANDLER_FAILURES: u32 = 3;
+
+thread_local! {
+    // when the handler that's running right now has to be done by.
+    static HANDLER_DEADLINE: Cell<Option<Instant>> = Cell::new(None);
+}
+
+// Dyon can't be interrupted from the outside, so the budget is checked whenever a handler
+// calls into the game, and at the start of every block, which the scripts are instrumented with.
+fn check_budget() -> Result<(), String> {
+    match HANDLER_DEADLINE.with(Cell::get) {
+        Some(deadline) if Instant::now() > deadline => Err(format!(
+            "Took longer than {}ms, so it was stopped.",
+            HANDLER_BUDGET.as_millis()
+        )),
+        _ => Ok(()),
+    }
+}
+
 // this stores everything that's needed to run Dyon code.
 pub struct DyonState {
     runtime: dyon::Runtime,
-    module: std::sync::Arc<dyon::Module>,
-    dyon_data: DyonData,
+    // just the library functions, the scripts are compiled on top of a clone of this.
+    base_module: dyon::Module,
+    // each script module, compiled on top of the base module.
+    scripts: crate::prelude::DyonScripts,
+    // how many times in a row each handler has failed,
+    // the ones that have hit MAX_HANDLER_FAILURES are skipped until the scripts are reloaded.
+    handler_failures: HashMap<String, u32>,
 }
 impl DyonState {
     pub fn new() -> Self {
+        Self::with_scripts(crate::prelude::SCRIPTS_DIRECTORY)
+    }
+
+    /// Like `new`, but the scripts come from somewhere other than `SCRIPTS_DIRECTORY`.
+    pub fn with_scripts<P: Into<std::path::PathBuf>>(directory: P) -> Self {
         use crate::prelude::*;
         use current::Current;
         use dyon::{dyon_fn, dyon_fn_pop, dyon_macro_items, Dfn, Lt, Module, Runtime, Type};
@@ -35,22 +252,51 @@ impl DyonState {
 
         let mut module = Module::new();
 
+        // every library function checks the time budget before doing anything,
+        // so a runaway handler gets stopped the next time it calls one of them.
+        macro_rules! add_budgeted {
+            ($name:ident, $dfn:expr $(,)?) => {{
+                fn budgeted(rt: &mut Runtime) -> Result<(), String> {
+                    check_budget()?;
+                    $name(rt)
+                }
+                module.add(Arc::new(stringify!($name).into()), budgeted, $dfn);
+            }};
+        }
+
         // library functions
 
+        // every block in the scripts starts off with a call to this, so loops can be stopped.
+        fn budget(_: &mut Runtime) -> Result<(), String> {
+            check_budget()
+        }
+        module.add(
+            Arc::new(BUDGET_CHECK.into()),
+            budget,
+            Dfn {
+                lts: vec![],
+                tys: vec![],
+                ret: Type::Void,
+            },
+        );
+
+        // scripts refer to entities by their persistent ids, not their specs::Entity
+        fn pop_entity(rt: &mut Runtime, world: &World) -> Result<specs::Entity, String> {
+            let id: u64 = rt.pop()?;
+            entity_from_id(world, id).ok_or_else(|| format!("There's no entity #{} around.", id))
+        }
+
         // immediately move an entity somewhere
         fn teleport_entity(rt: &mut Runtime) -> Result<(), String> {
             let world = unsafe { Current::<World>::new() };
 
             let coord_arr: [f32; 3] = rt.pop_vec4()?;
-            let ent_id: u32 = rt.pop()?;
+            let ent = pop_entity(rt, &world)?;
 
             // physics stuff
             let physes = world.read_storage::<Phys>();
             let mut ps = world.write_resource::<PhysState>();
-            // entity stuff
-            let ents = world.entities();
             let coords = glm::make_vec3(&coord_arr);
-            let ent = ents.entity(ent_id);
 
             let phys = physes
                 .get(ent)
@@ -60,8 +306,7 @@ impl DyonState {
 
             Ok(())
         }
-        module.add(
-            Arc::new("teleport_entity".into()),
+        add_budgeted!(
             teleport_entity,
             Dfn {
                 lts: vec![Lt::Default, Lt::Default],
@@ -73,7 +318,7 @@ impl DyonState {
         // get the cache of saveable dyon data for a certain entity
         fn get_cache_entity(rt: &mut Runtime) -> Result<(), String> {
             let mut dyon_data = unsafe { Current::<DyonData>::new() };
-            let ent = rt.pop::<u32>()?;
+            let ent = rt.pop::<u64>()?;
 
             dyon_data.cache_refs.insert(ent, rt.stack.len());
             let cache = dyon_data
@@ -84,8 +329,7 @@ impl DyonState {
 
             Ok(())
         }
-        module.add(
-            Arc::new("get_cache_entity".into()),
+        add_budgeted!(
             get_cache_entity,
             Dfn {
                 lts: vec![Lt::Default],
@@ -113,8 +357,7 @@ impl DyonState {
 
             Ok(())
         }
-        module.add(
-            Arc::new("save_caches".into()),
+        add_budgeted!(
             save_caches,
             Dfn {
                 lts: vec![],
@@ -124,23 +367,22 @@ impl DyonState {
         );
 
         // get an array of things with this scripting id
-        dyon_fn! {fn all_with_id(search_id: String) -> Vec<u32> {
+        dyon_fn! {fn all_with_id(search_id: String) -> Vec<u64> {
             let world = unsafe { Current::<World>::new() };
 
-            let ents = world.entities();
+            let markers = world.read_storage::<U64Marker>();
             let scripting_ids = world.read_storage::<ScriptingIds>();
 
-            (&ents, &scripting_ids)
+            (&markers, &scripting_ids)
                 .join()
                 // find the entities whose list of scripting_ids contain search_id
                 .filter(|(_, ScriptingIds { ids })| ids.contains(&search_id))
                 // dyon only deals with the id # of the entities, not the entity structs.
-                .map(|(ent, _)| ent.id())
+                .map(|(marker, _)| marker.id())
                 // okay now vec that thing and ship it off
                 .collect::<Vec<_>>()
         }}
-        module.add(
-            Arc::new("all_with_id".into()),
+        add_budgeted!(
             all_with_id,
             Dfn {
                 lts: vec![Lt::Default],
@@ -151,21 +393,18 @@ impl DyonState {
 
         fn add_id_entity(rt: &mut Runtime) -> Result<(), String> {
             let world = unsafe { Current::<World>::new() };
-            let ents = world.entities();
             let mut scripting_ids = world.write_storage::<ScriptingIds>();
 
             let scripting_id: String = rt.pop()?;
-            let ent_id: u32 = rt.pop()?;
+            let ent = pop_entity(rt, &world)?;
 
-            let ent = ents.entity(ent_id);
             let ent_ids = scripting_ids
                 .get_mut(ent)
                 .ok_or("That entity doesn't exist, or doesn't/can't have scripting ids.")?;
             ent_ids.ids.push(scripting_id);
             Ok(())
         }
-        module.add(
-            Arc::new("add_id_entity".into()),
+        add_budgeted!(
             add_id_entity,
             Dfn {
                 lts: vec![Lt::Default, Lt::Default],
@@ -176,21 +415,18 @@ impl DyonState {
 
         fn has_id_entity(rt: &mut Runtime) -> Result<(), String> {
             let world = unsafe { Current::<World>::new() };
-            let ents = world.entities();
             let scripting_ids = world.read_storage::<ScriptingIds>();
 
             let scripting_id: String = rt.pop()?;
-            let ent_id: u32 = rt.pop()?;
+            let ent = pop_entity(rt, &world)?;
 
-            let ent = ents.entity(ent_id);
             match scripting_ids.get(ent) {
                 Some(ent_ids) => rt.push(ent_ids.ids.contains(&scripting_id)),
                 None => rt.push(false),
             }
             Ok(())
         }
-        module.add(
-            Arc::new("has_id_entity".into()),
+        add_budgeted!(
             has_id_entity,
             Dfn {
                 lts: vec![Lt::Default, Lt::Default],
@@ -204,19 +440,21 @@ impl DyonState {
 
             // I want all of these to be dropped before I do world.maintain()
             {
-                let ent_id: u32 = rt.pop()?;
+                let id: u64 = rt.pop()?;
+                let ent = entity_from_id(world, id).ok_or("That entity's already gone.")?;
 
-                let entities = world.entities();
+                world.entities().delete(ent).unwrap();
 
-                entities.delete(entities.entity(ent_id)).unwrap();
+                // it won't be coming back, so neither will its cache.
+                let mut dyon_data = unsafe { Current::<DyonData>::new() };
+                dyon_data.remove_cache(id);
             }
 
             world.maintain();
 
             Ok(())
         }
-        module.add(
-            Arc::new("delete_entity".into()),
+        add_budgeted!(
             delete_entity,
             Dfn {
                 lts: vec![Lt::Default],
@@ -229,10 +467,10 @@ impl DyonState {
         fn spawn_at_entity(rt: &mut Runtime) -> Result<(), String> {
             let world = unsafe { &mut *Current::<World>::new() };
 
-            {
+            let spawned = {
                 // okay now get the entity, their position, and what to spawn
                 let what_to_spawn: String = rt.pop()?;
-                let ent_id: u32 = rt.pop()?;
+                let ent = pop_entity(rt, &world)?;
 
                 // resources
                 let ps = world.read_resource::<PhysState>();
@@ -241,21 +479,29 @@ impl DyonState {
                 let ents = world.entities();
                 let physes = world.read_storage::<Phys>();
 
+                if !assemblager.assemblages.contains_key(&what_to_spawn) {
+                    return Err(format!(
+                        "There's no type called {:?} to spawn.",
+                        what_to_spawn
+                    ));
+                }
+
                 // action
-                let ent = ents.entity(ent_id);
                 let pos = physes
                     .get(ent)
                     .and_then(|phys| ps.location(phys))
                     .ok_or("can't spawn at an entity which has no position")?;
 
-                rt.push(assemblager.build_at(&what_to_spawn, &lu, &ents, *pos).id());
-            }
+                assemblager.build_at(&what_to_spawn, &lu, &ents, *pos)
+            };
 
+            // it doesn't get its id until it's actually been built
             world.maintain();
+            rt.push(id_of_entity(world, spawned).ok_or("The spawned entity has no id.")?);
+
             Ok(())
         }
-        module.add(
-            Arc::new("spawn_at_entity".into()),
+        add_budgeted!(
             spawn_at_entity,
             Dfn {
                 lts: vec![Lt::Default, Lt::Default],
@@ -269,10 +515,8 @@ impl DyonState {
             let world = unsafe { Current::<World>::new() };
 
             let health_value: f32 = rt.pop()?;
-            let ent_id: u32 = rt.pop()?;
+            let ent = pop_entity(rt, &world)?;
 
-            let ents = world.entities();
-            let ent = ents.entity(ent_id);
             let mut health_storage = world.write_storage::<Health>();
 
             let mut health = health_storage
@@ -282,8 +526,7 @@ impl DyonState {
 
             Ok(())
         }
-        module.add(
-            Arc::new("set_hp_entity".into()),
+        add_budgeted!(
             set_hp_entity,
             Dfn {
                 lts: vec![Lt::Default, Lt::Default],
@@ -297,10 +540,8 @@ impl DyonState {
             let world = unsafe { Current::<World>::new() };
 
             let percent: f32 = rt.pop()?;
-            let ent_id: u32 = rt.pop()?;
+            let ent = pop_entity(rt, &world)?;
 
-            let ents = world.entities();
-            let ent = ents.entity(ent_id);
             let mut health_storage = world.write_storage::<Health>();
 
             let mut health = health_storage
@@ -311,8 +552,7 @@ impl DyonState {
 
             Ok(())
         }
-        module.add(
-            Arc::new("set_hp_percent_entity".into()),
+        add_budgeted!(
             set_hp_percent_entity,
             Dfn {
                 lts: vec![Lt::Default, Lt::Default],
@@ -326,20 +566,17 @@ impl DyonState {
             let world = unsafe { Current::<World>::new() };
 
             let health_value = rt.pop::<f32>()?;
-            let ent_id: u32 = rt.pop()?;
+            let ent = pop_entity(rt, &world)?;
 
-            let ents = world.entities();
             let mut health_storage = world.write_storage::<Health>();
-            let ent = ents.entity(ent_id);
             let mut health = health_storage
                 .get_mut(ent)
                 .ok_or("Entity does not have health component")?;
-            health.value += health.max.min(health.value + health_value);
+            health.value = health.max.min(health.value + health_value);
 
             Ok(())
         }
-        module.add(
-            Arc::new("change_hp_entity".into()),
+        add_budgeted!(
             change_hp_entity,
             Dfn {
                 lts: vec![Lt::Default, Lt::Default],
@@ -353,169 +590,1774 @@ impl DyonState {
             let world = unsafe { Current::<World>::new() };
 
             let percent: f32 = rt.pop()?;
-            let ent_id: u32 = rt.pop()?;
+            let ent = pop_entity(rt, &world)?;
 
-            let ents = world.entities();
-            let ent = ents.entity(ent_id);
             let mut health_storage = world.write_storage::<Health>();
             let mut health = health_storage
                 .get_mut(ent)
                 .ok_or("Entity does not have health component")?;
-            health.value += health.max.min((percent / 100.0) * health.value);
+            health.value = health
+                .max
+                .min(health.value + (percent / 100.0) * health.value);
 
             Ok(())
         }
-        module.add(
-            Arc::new("change_hp_percent_entity".into()),
+        add_budgeted!(
             change_hp_percent_entity,
+            Dfn {
+                lts: vec![Lt::Default, Lt::Default],
+                tys: vec![Type::F64, Type::F64],
+                ret: Type::Void,
+            },
+        );
+
+        // deal damage of a kind, like "fire", to an entity. unlike changing its hp,
+        // this goes through its resistances and invulnerability, and can kill it.
+        // the damage is taken on the next tick, and negative damage heals.
+        fn deal_damage(world: &World, ent: specs::Entity, hit: DamageHit) -> Result<(), String> {
+            if world.read_storage::<Health>().get(ent).is_none() {
+                return Err("Damage dealt to entity without health component".to_owned());
+            }
+            Damage::deal(&mut world.write_storage::<Damage>(), ent, hit);
+            Ok(())
+        }
+
+        fn damage_entity(rt: &mut Runtime) -> Result<(), String> {
+            let world = unsafe { Current::<World>::new() };
+
+            let kind: String = rt.pop()?;
+            let amount: f32 = rt.pop()?;
+            let ent = pop_entity(rt, &world)?;
+
+            deal_damage(
+                &world,
+                ent,
+                DamageHit {
+                    amount,
+                    kind,
+                    source: None,
+                },
+            )
+        }
+        add_budgeted!(
+            damage_entity,
+            Dfn {
+                lts: vec![Lt::Default, Lt::Default, Lt::Default],
+                tys: vec![Type::F64, Type::F64, Type::Text],
+                ret: Type::Void,
+            },
+        );
+
+        // the same, but whoever did it gets the credit if it dies.
+        fn damage_entity_by(rt: &mut Runtime) -> Result<(), String> {
+            let world = unsafe { Current::<World>::new() };
+
+            let kind: String = rt.pop()?;
+            let amount: f32 = rt.pop()?;
+            let source: u64 = rt.pop()?;
+            let ent = pop_entity(rt, &world)?;
+
+            deal_damage(
+                &world,
+                ent,
+                DamageHit {
+                    amount,
+                    kind,
+                    source: Some(source),
+                },
+            )
+        }
+        add_budgeted!(
+            damage_entity_by,
+            Dfn {
+                lts: vec![Lt::Default, Lt::Default, Lt::Default, Lt::Default],
+                tys: vec![Type::F64, Type::F64, Type::F64, Type::Text],
+                ret: Type::Void,
+            },
+        );
+
+        // swing, or throw, whatever the entity's holding the next time attacks happen.
+        // it attacks toward the direction, unless that's all 0s.
+        fn attack_entity(rt: &mut Runtime) -> Result<(), String> {
+            let world = unsafe { Current::<World>::new() };
+
+            let direction: [f32; 3] = rt.pop_vec4()?;
+            let ent = pop_entity(rt, &world)?;
+
+            let mut attacks = world.write_storage::<Attack>();
+            let attack = attacks
+                .get_mut(ent)
+                .ok_or("Attack requested for entity without an attack.")?;
+            let direction = glm::vec2(direction[0], direction[1]);
+            if glm::length(&direction) > 0.0 {
+                let direction = direction.normalize();
+                attack.facing = [direction.x, direction.y];
+            }
+            attack.triggered = true;
+
+            Ok(())
+        }
+        add_budgeted!(
+            attack_entity,
+            Dfn {
+                lts: vec![Lt::Default, Lt::Default],
+                tys: vec![Type::F64, Type::Vec4],
+                ret: Type::Void,
+            },
+        );
+
+        // give an entity the Attack of a weapon from types.json, like `equip_entity(e, "hammer")`
+        fn equip_entity(rt: &mut Runtime) -> Result<(), String> {
+            let world = unsafe { Current::<World>::new() };
+
+            let weapon: String = rt.pop()?;
+            let ent = pop_entity(rt, &world)?;
+
+            let attack = world
+                .read_resource::<Assemblager>()
+                .component::<Attack>(&weapon)
+                .cloned()
+                .ok_or_else(|| format!("There's no weapon called {}.", weapon))?;
+            world.write_storage::<Attack>().insert(ent, attack).unwrap();
+
+            Ok(())
+        }
+        add_budgeted!(
+            equip_entity,
+            Dfn {
+                lts: vec![Lt::Default, Lt::Default],
+                tys: vec![Type::F64, Type::Text],
+                ret: Type::Void,
+            },
+        );
+
+        // which state an NPC's AiBehaviour is in, or "" if it hasn't started thinking yet.
+        fn ai_state_entity(rt: &mut Runtime) -> Result<(), String> {
+            let world = unsafe { Current::<World>::new() };
+            let ent = pop_entity(rt, &world)?;
+
+            let ais = world.read_storage::<AiBehaviour>();
+            let ai = ais
+                .get(ent)
+                .ok_or("AI state requested for entity without an AiBehaviour.")?;
+            rt.push(ai.current.clone().unwrap_or_default());
+            Ok(())
+        }
+        add_budgeted!(
+            ai_state_entity,
             Dfn {
                 lts: vec![Lt::Default],
                 tys: vec![Type::F64],
+                ret: Type::Text,
+            },
+        );
+
+        // put an NPC into another state right away, its on_enter handler is called next tick.
+        fn set_ai_state_entity(rt: &mut Runtime) -> Result<(), String> {
+            let world = unsafe { Current::<World>::new() };
+
+            let state: String = rt.pop()?;
+            let id: u64 = rt.pop()?;
+            let ent = entity_from_id(&world, id)
+                .ok_or_else(|| format!("There's no entity #{} around.", id))?;
+
+            let mut ais = world.write_storage::<AiBehaviour>();
+            let ai = ais
+                .get_mut(ent)
+                .ok_or("AI state change requested for entity without an AiBehaviour.")?;
+            if let Some(event) = ai.enter(&state)? {
+                world.write_resource::<ScriptEvents>().push(id, event);
+            }
+            Ok(())
+        }
+        add_budgeted!(
+            set_ai_state_entity,
+            Dfn {
+                lts: vec![Lt::Default, Lt::Default],
+                tys: vec![Type::F64, Type::Text],
                 ret: Type::Void,
             },
         );
 
-        // log a message into the Dyon console in the DevUi
-        dyon_fn! {fn log(msg: String) {
+        // where an entity is
+        fn position_entity(rt: &mut Runtime) -> Result<(), String> {
             let world = unsafe { Current::<World>::new() };
-            let mut dyon_console = world.write_resource::<DyonConsole>();
+            let ent = pop_entity(rt, &world)?;
 
-            // get the message they passed as an argument to the function
-            dyon_console.0.push_str(&format!("{}\n", msg));
-        }}
-        module.add(
-            Arc::new("log".into()),
-            log,
+            let physes = world.read_storage::<Phys>();
+            let ps = world.read_resource::<PhysState>();
+            let location = physes
+                .get(ent)
+                .and_then(|phys| ps.location(phys))
+                .ok_or("Position requested for non-physical entity.")?;
+
+            rt.push(Variable::Vec4([location.x, location.y, location.z, 0.0]));
+            Ok(())
+        }
+        add_budgeted!(
+            position_entity,
             Dfn {
                 lts: vec![Lt::Default],
-                tys: vec![Type::Text],
-                ret: Type::Void,
+                tys: vec![Type::F64],
+                ret: Type::Vec4,
             },
         );
 
-        // finally, return the instance with the filled module and runtime.
-        Self {
-            runtime,
-            module: Arc::new(module),
-            dyon_data: DyonData::default(),
+        // how fast an entity is moving, and which way
+        fn velocity_entity(rt: &mut Runtime) -> Result<(), String> {
+            let world = unsafe { Current::<World>::new() };
+            let ent = pop_entity(rt, &world)?;
+
+            let physes = world.read_storage::<Phys>();
+            let ps = world.read_resource::<PhysState>();
+            let velocity = physes
+                .get(ent)
+                .and_then(|phys| ps.rigid_body(phys))
+                .ok_or("Velocity requested for non-physical entity.")?
+                .velocity()
+                .linear;
+
+            rt.push(Variable::Vec4([velocity.x, velocity.y, velocity.z, 0.0]));
+            Ok(())
         }
-    }
+        add_budgeted!(
+            velocity_entity,
+            Dfn {
+                lts: vec![Lt::Default],
+                tys: vec![Type::F64],
+                ret: Type::Vec4,
+            },
+        );
 
-    pub fn run(&mut self) {
-        use crate::prelude::*;
-        use current::Current;
-        use dyon::{load, Call};
-        use specs::Join;
+        // immediately change how fast an entity's moving
+        fn set_velocity_entity(rt: &mut Runtime) -> Result<(), String> {
+            use nphysics3d::{math::Velocity, object::Body};
+            let world = unsafe { Current::<World>::new() };
 
-        // I'm fairly sure this world has to be dropped
-        let script_events = {
-            let world = unsafe { &*Current::<specs::World>::new() };
-            let entities = world.entities();
-            let mut event_storage = world.write_storage::<ScriptEvent>();
-            (event_storage.drain(), &entities)
-                .join()
-                // we only want the ids for our purposes,
-                .map(|(e, x)| (e, x.id()))
-                .collect::<Vec<_>>()
-                // copy so no references to the world remain!
-                .clone()
-        };
-        let mut events_iter = script_events.iter().peekable();
-
-        // if there's actually at least one event to bother cloning the module for...
-        if events_iter.peek().is_some() {
-            // manually split the borrow for the borrow checker
-            let dyon_data = &mut self.dyon_data;
-            let runtime = &mut self.runtime;
-
-            // reload the code containing the function they want in case they changed it
-            let mut module = (*self.module).clone();
-            let module_load_error = load("src/dyon/test.dyon", &mut module)
-                .err()
-                // these are almost always syntax errors but I guess they could
-                // be other things actually? whatever I'll just leave it this way.
-                .map(|x| format!(" --- SYNTAX ERROR --- \n{}\n\n", x));
-            let module = Arc::new(module);
-
-            // open up the DyonData for access by the scripts
-            let dyon_data_guard = CurrentGuard::new(dyon_data);
-
-            // output
-            let output = script_events
-                .iter()
-                // call each script_event's handler, and collect the errors
-                .map(|(script_event, id)| {
-                    let event_handler = Call::new(&script_event.function)
-                        .arg(*id)
-                        .arg(script_event.payload);
-                    event_handler
-                        .run(runtime, &Arc::clone(&module))
-                        .map_err(|err| format!("fn {}: \n {}", script_event.function, err))
-                })
-                // now combine all of the errors into one,
-                .fold(
-                    // starting with the errors from loading the module,
-                    // or an empty string if there were none,
-                    module_load_error.unwrap_or(String::new()),
-                    // then for each script event that was run,
-                    // add its error too if it emitted one.
-                    |mut acc, res| {
-                        match res {
-                            // we only care about the errors
-                            Err(err) => {
-                                acc.push_str(&format!(" --- ERROR --- \n{}\n\n", err));
-                            }
-                            _ => {}
-                        };
-                        acc
+            let velocity: [f32; 3] = rt.pop_vec4()?;
+            let ent = pop_entity(rt, &world)?;
+
+            let physes = world.read_storage::<Phys>();
+            let mut ps = world.write_resource::<PhysState>();
+            let body = physes
+                .get(ent)
+                .and_then(|phys| ps.rigid_body_mut(phys))
+                .ok_or("Velocity change requested for non-physical entity.")?;
+
+            let angular = body.velocity().angular;
+            body.set_velocity(Velocity::new(glm::make_vec3(&velocity), angular));
+            // it won't go anywhere if it's asleep.
+            body.activate();
+
+            Ok(())
+        }
+        add_budgeted!(
+            set_velocity_entity,
+            Dfn {
+                lts: vec![Lt::Default, Lt::Default],
+                tys: vec![Type::F64, Type::Vec4],
+                ret: Type::Void,
+            },
+        );
+
+        // push an entity around for a while, like an explosion would
+        fn apply_force_entity(rt: &mut Runtime) -> Result<(), String> {
+            let world = unsafe { Current::<World>::new() };
+
+            let duration: f32 = rt.pop()?;
+            let force: [f32; 3] = rt.pop_vec4()?;
+            let ent = pop_entity(rt, &world)?;
+
+            world
+                .write_storage::<ApplyForce>()
+                .insert(
+                    ent,
+                    ApplyForce {
+                        vec: glm::make_vec3(&force),
+                        duration,
+                        ..ApplyForce::default()
                     },
-                );
+                )
+                .map_err(|_| "That entity's already gone.")?;
 
-            // now that all of the modules are done accessing it,
-            drop(dyon_data_guard);
+            Ok(())
+        }
+        add_budgeted!(
+            apply_force_entity,
+            Dfn {
+                lts: vec![Lt::Default, Lt::Default, Lt::Default],
+                tys: vec![Type::F64, Type::Vec4, Type::F64],
+                ret: Type::Void,
+            },
+        );
 
-            // quickly add the errors that could've been outputted to the console
-            let world = unsafe { &*Current::<specs::World>::new() };
-            let mut dyon_console = world.write_resource::<DyonConsole>();
-            dyon_console.0.push_str(&output);
+        // the name of the image an entity's drawn with
+        fn image_entity(rt: &mut Runtime) -> Result<(), String> {
+            let world = unsafe { Current::<World>::new() };
+            let ent = pop_entity(rt, &world)?;
+
+            let builders = world.read_storage::<AppearanceBuilder>();
+            let builder = builders
+                .get(ent)
+                .ok_or("Entity does not have an AppearanceBuilder component")?;
+
+            rt.push(Variable::Text(Arc::new(builder.image_name.clone())));
+            Ok(())
         }
-    }
-}
+        add_budgeted!(
+            image_entity,
+            Dfn {
+                lts: vec![Lt::Default],
+                tys: vec![Type::F64],
+                ret: Type::Text,
+            },
+        );
+
+        // draw an entity with a different image from the spritesheet
+        fn set_image_entity(rt: &mut Runtime) -> Result<(), String> {
+            let world = unsafe { Current::<World>::new() };
 
-// this is the fancy little struct that stores all of the data
-// for the scripts. It's formatted this way so that the data can
-// also be saved, when that needs to occur.
+            let image_name: String = rt.pop()?;
+            let ent = pop_entity(rt, &world)?;
 
-/*
-#[derive(Default)]
-pub struct DyonCache {
-    pub data: dyon::Object,
-}
+            if !world
+                .read_resource::<ImageBundle>()
+                .map
+                .contains_key(&image_name)
+            {
+                return Err(format!("There's no image called {}.", image_name));
+            }
+
+            let mut builders = world.write_storage::<AppearanceBuilder>();
+            let builder = builders
+                .get_mut(ent)
+                .ok_or("Entity does not have an AppearanceBuilder component")?;
+
+            builder.image_name = image_name;
+            // so the Appearance gets built again with the new image
+            builder.built = false;
+
+            Ok(())
+        }
+        add_budgeted!(
+            set_image_entity,
+            Dfn {
+                lts: vec![Lt::Default, Lt::Default],
+                tys: vec![Type::F64, Type::Text],
+                ret: Type::Void,
+            },
+        );
+
+        // change how many frames an entity's animation has, and how fast it goes through them
+        fn set_animation_entity(rt: &mut Runtime) -> Result<(), String> {
+            let world = unsafe { Current::<World>::new() };
+
+            let fps: f32 = rt.pop()?;
+            let frame_count: f64 = rt.pop()?;
+            let ent = pop_entity(rt, &world)?;
 
-use serde::ser::{Error, Serialize, SerializeMap, Serializer};
-impl Serialize for DyonCache {
-    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
-    where
-        S: Serializer,
-    {
-        use dyon::Variable::*;
-
-        let mut map = serializer.serialize_map(Some(self.data.len()))?;
-
-        for (k, v) in self.data.iter() {
-            map.serialize_entry(
-                &**k,
-                &(match v {
-                    Text(dyon_string) => Ok(dyon_string.to_string()),
-                    F64(dyon_num, _) => Ok(dyon_num.to_string()),
-                    Bool(dyon_bool, _) => Ok(dyon_bool.to_string()),
-                    _ => Err(S::Error::custom(
-                        "The game can't save a variable of that type!",
-                    )),
-                }?),
-            )?;
-        }
-
-        map.end()
-    }
-}*/
+            // the frame it's on is worked out with a modulo by the frame count.
+            if !frame_count.is_finite() || frame_count < 1.0 {
+                return Err(format!("An animation can't have {} frames.", frame_count));
+            }
+            if !fps.is_finite() {
+                return Err(format!("An animation can't go at {} fps.", fps));
+            }
+
+            let mut animations = world.write_storage::<Animation>();
+            let animation = animations
+                .get_mut(ent)
+                .ok_or("Entity does not have an Animation component")?;
+
+            animation.frame_count = frame_count as i32;
+            animation.fps = fps;
+
+            Ok(())
+        }
+        add_budgeted!(
+            set_animation_entity,
+            Dfn {
+                lts: vec![Lt::Default, Lt::Default, Lt::Default],
+                tys: vec![Type::F64, Type::F64, Type::F64],
+                ret: Type::Void,
+            },
+        );
+
+        // give an entity any component the Assemblager knows about,
+        // with whatever fields aren't in the object left as their defaults.
+        fn add_component_entity(rt: &mut Runtime) -> Result<(), String> {
+            let world = unsafe { Current::<World>::new() };
+
+            let fields = {
+                let fields = rt.stack.pop().ok_or("There's nothing on the stack.")?;
+                variable_to_json(rt.resolve(&fields))?
+            };
+            let name: String = rt.pop()?;
+            let ent = pop_entity(rt, &world)?;
+
+            let component = world
+                .read_resource::<Assemblager>()
+                .build_component(&name, fields)?;
+            component.copy_self_to(&world, &ent);
+
+            Ok(())
+        }
+        add_budgeted!(
+            add_component_entity,
+            Dfn {
+                lts: vec![Lt::Default, Lt::Default, Lt::Default],
+                tys: vec![Type::F64, Type::Text, Type::Object],
+                ret: Type::Void,
+            },
+        );
+
+        // take a component away from an entity, by name
+        fn remove_component_entity(rt: &mut Runtime) -> Result<(), String> {
+            let world = unsafe { Current::<World>::new() };
+
+            let name: String = rt.pop()?;
+            let ent = pop_entity(rt, &world)?;
+
+            world
+                .read_resource::<Assemblager>()
+                .components
+                .get(&imgui::ImString::new(name.as_str()))
+                .ok_or_else(|| format!("There's no component called {}.", name))?
+                .remove_from_entity(&world, &ent);
+
+            Ok(())
+        }
+        add_budgeted!(
+            remove_component_entity,
+            Dfn {
+                lts: vec![Lt::Default, Lt::Default],
+                tys: vec![Type::F64, Type::Text],
+                ret: Type::Void,
+            },
+        );
+
+        // call a handler on an entity some seconds from now, returning a handle for cancelling it.
+        // the handler gets the entity and the handle, like `fn ring(ent, handle)`
+        dyon_fn! {fn after(seconds: f64, function: String, entity: u64) -> u64 {
+            let world = unsafe { Current::<World>::new() };
+            let mut scheduler = world.write_resource::<ScriptScheduler>();
+            scheduler.after(seconds as f32, function, entity)
+        }}
+        add_budgeted!(
+            after,
+            Dfn {
+                lts: vec![Lt::Default, Lt::Default, Lt::Default],
+                tys: vec![Type::F64, Type::Text, Type::F64],
+                ret: Type::F64,
+            },
+        );
+
+        // like `after`, but over and over again until it's cancelled.
+        dyon_fn! {fn every(seconds: f64, function: String, entity: u64) -> u64 {
+            let world = unsafe { Current::<World>::new() };
+            let mut scheduler = world.write_resource::<ScriptScheduler>();
+            scheduler.every(seconds as f32, function, entity)
+        }}
+        add_budgeted!(
+            every,
+            Dfn {
+                lts: vec![Lt::Default, Lt::Default, Lt::Default],
+                tys: vec![Type::F64, Type::Text, Type::F64],
+                ret: Type::F64,
+            },
+        );
+
+        // stop a timer from `after` or `every`, returns false if it was already done.
+        dyon_fn! {fn cancel(handle: u64) -> bool {
+            let world = unsafe { Current::<World>::new() };
+            let mut scheduler = world.write_resource::<ScriptScheduler>();
+            scheduler.cancel(handle)
+        }}
+        add_budgeted!(
+            cancel,
+            Dfn {
+                lts: vec![Lt::Default],
+                tys: vec![Type::F64],
+                ret: Type::Bool,
+            },
+        );
+
+        // how many of an item an entity has, 0 if it doesn't have an inventory.
+        fn item_count_entity(rt: &mut Runtime) -> Result<(), String> {
+            let world = unsafe { Current::<World>::new() };
+
+            let key: String = rt.pop()?;
+            let ent = pop_entity(rt, &world)?;
+
+            let count = world
+                .read_storage::<Inventory>()
+                .get(ent)
+                .map_or(0, |inventory| inventory.count(&key));
+            rt.push(count as f64);
+            Ok(())
+        }
+        add_budgeted!(
+            item_count_entity,
+            Dfn {
+                lts: vec![Lt::Default, Lt::Default],
+                tys: vec![Type::F64, Type::Text],
+                ret: Type::F64,
+            },
+        );
+
+        // everything in an entity's inventory, like `{melon: 3}`
+        fn inventory_entity(rt: &mut Runtime) -> Result<(), String> {
+            let world = unsafe { Current::<World>::new() };
+            let ent = pop_entity(rt, &world)?;
+
+            let inventories = world.read_storage::<Inventory>();
+            let inventory = inventories
+                .get(ent)
+                .ok_or("Inventory requested for entity without one.")?;
+            rt.push(Variable::Object(Arc::new(
+                inventory
+                    .totals()
+                    .into_iter()
+                    .map(|(key, count)| (Arc::new(key), Variable::F64(count as f64, None)))
+                    .collect(),
+            )));
+            Ok(())
+        }
+        add_budgeted!(
+            inventory_entity,
+            Dfn {
+                lts: vec![Lt::Default],
+                tys: vec![Type::F64],
+                ret: Type::Object,
+            },
+        );
+
+        // put items in an entity's inventory, returns how many didn't fit.
+        fn give_item_entity(rt: &mut Runtime) -> Result<(), String> {
+            let world = unsafe { Current::<World>::new() };
+
+            // negative amounts don't mean anything for items
+            let quantity = rt.pop::<f64>()?.max(0.0) as u32;
+            let key: String = rt.pop()?;
+            let ent = pop_entity(rt, &world)?;
+
+            let definitions = world.read_resource::<ItemDefinitions>();
+            let mut inventories = world.write_storage::<Inventory>();
+            let inventory = inventories
+                .get_mut(ent)
+                .ok_or("Items given to entity without an inventory.")?;
+            let left_over = inventory.give(&key, quantity, definitions.get(&key)?);
+            rt.push(left_over as f64);
+            Ok(())
+        }
+        add_budgeted!(
+            give_item_entity,
+            Dfn {
+                lts: vec![Lt::Default, Lt::Default, Lt::Default],
+                tys: vec![Type::F64, Type::Text, Type::F64],
+                ret: Type::F64,
+            },
+        );
+
+        // take items out of an entity's inventory, but only if it has that many.
+        // returns whether or not they were taken.
+        fn take_item_entity(rt: &mut Runtime) -> Result<(), String> {
+            let world = unsafe { Current::<World>::new() };
+
+            // negative amounts don't mean anything for items
+            let quantity = rt.pop::<f64>()?.max(0.0) as u32;
+            let key: String = rt.pop()?;
+            let ent = pop_entity(rt, &world)?;
+
+            let taken = world
+                .write_storage::<Inventory>()
+                .get_mut(ent)
+                .map_or(false, |inventory| inventory.take(&key, quantity));
+            rt.push(taken);
+            Ok(())
+        }
+        add_budgeted!(
+            take_item_entity,
+            Dfn {
+                lts: vec![Lt::Default, Lt::Default, Lt::Default],
+                tys: vec![Type::F64, Type::Text, Type::F64],
+                ret: Type::Bool,
+            },
+        );
+
+        // quests are started, advanced and completed by name, like `start_quest("melon delivery")`.
+        // each returns false if the quest was already started, or wasn't active.
+        fn start_quest(rt: &mut Runtime) -> Result<(), String> {
+            let world = unsafe { Current::<World>::new() };
+            let name: String = rt.pop()?;
+
+            let quests = world.read_resource::<Quests>();
+            let started = world.write_resource::<QuestLog>().start(&quests, &name)?;
+            rt.push(started);
+            Ok(())
+        }
+        add_budgeted!(
+            start_quest,
+            Dfn {
+                lts: vec![Lt::Default],
+                tys: vec![Type::Text],
+                ret: Type::Bool,
+            },
+        );
+
+        // skip to the next stage of a quest
+        fn advance_quest(rt: &mut Runtime) -> Result<(), String> {
+            let world = unsafe { Current::<World>::new() };
+            let name: String = rt.pop()?;
+
+            let quests = world.read_resource::<Quests>();
+            let advanced = world.write_resource::<QuestLog>().advance(&quests, &name)?;
+            rt.push(advanced);
+            Ok(())
+        }
+        add_budgeted!(
+            advance_quest,
+            Dfn {
+                lts: vec![Lt::Default],
+                tys: vec![Type::Text],
+                ret: Type::Bool,
+            },
+        );
+
+        // finish a quest, the rewards are handed out next tick.
+        fn complete_quest(rt: &mut Runtime) -> Result<(), String> {
+            let world = unsafe { Current::<World>::new() };
+            let name: String = rt.pop()?;
+
+            let quests = world.read_resource::<Quests>();
+            let completed = world
+                .write_resource::<QuestLog>()
+                .complete(&quests, &name)?;
+            rt.push(completed);
+            Ok(())
+        }
+        add_budgeted!(
+            complete_quest,
+            Dfn {
+                lts: vec![Lt::Default],
+                tys: vec![Type::Text],
+                ret: Type::Bool,
+            },
+        );
+
+        // "not started", "active" or "complete"
+        dyon_fn! {fn quest_status(name: String) -> String {
+            let world = unsafe { Current::<World>::new() };
+            match world.read_resource::<QuestLog>().status(&name) {
+                QuestStatus::NotStarted => "not started",
+                QuestStatus::Active => "active",
+                QuestStatus::Complete => "complete",
+            }
+            .to_owned()
+        }}
+        add_budgeted!(
+            quest_status,
+            Dfn {
+                lts: vec![Lt::Default],
+                tys: vec![Type::Text],
+                ret: Type::Text,
+            },
+        );
+
+        // which stage an active quest is on, starting from 0. it's -1 if the quest isn't active.
+        dyon_fn! {fn quest_stage(name: String) -> f64 {
+            let world = unsafe { Current::<World>::new() };
+            world
+                .read_resource::<QuestLog>()
+                .stage(&name)
+                .map_or(-1.0, |stage| stage as f64)
+        }}
+        add_budgeted!(
+            quest_stage,
+            Dfn {
+                lts: vec![Lt::Default],
+                tys: vec![Type::Text],
+                ret: Type::F64,
+            },
+        );
+
+        // what the REPL uses to show what an expression came out to.
+        fn show_repl_result(rt: &mut Runtime, entities: bool) -> Result<(), String> {
+            let world = unsafe { Current::<World>::new() };
+
+            let value = rt.stack.pop().ok_or("There's nothing on the stack.")?;
+            let shown = describe_variable(rt.resolve(&value), &world, entities);
+            world
+                .write_resource::<DyonConsole>()
+                .0
+                .push_str(&format!("{}\n", shown));
+
+            Ok(())
+        }
+        fn repl_result(rt: &mut Runtime) -> Result<(), String> {
+            show_repl_result(rt, false)
+        }
+        add_budgeted!(
+            repl_result,
+            Dfn {
+                lts: vec![Lt::Default],
+                tys: vec![Type::Any],
+                ret: Type::Void,
+            },
+        );
+        // for lines that call one of the ENTITY_FUNCTIONS.
+        fn repl_entities(rt: &mut Runtime) -> Result<(), String> {
+            show_repl_result(rt, true)
+        }
+        add_budgeted!(
+            repl_entities,
+            Dfn {
+                lts: vec![Lt::Default],
+                tys: vec![Type::Any],
+                ret: Type::Void,
+            },
+        );
+
+        // log a message into the Dyon console in the DevUi
+        dyon_fn! {fn log(msg: String) {
+            let world = unsafe { Current::<World>::new() };
+            let mut dyon_console = world.write_resource::<DyonConsole>();
+
+            // get the message they passed as an argument to the function
+            dyon_console.0.push_str(&format!("{}\n", msg));
+        }}
+        add_budgeted!(
+            log,
+            Dfn {
+                lts: vec![Lt::Default],
+                tys: vec![Type::Text],
+                ret: Type::Void,
+            },
+        );
+
+        // finally, return the instance with the filled module and runtime.
+        // the scripts themselves are compiled the first time they're run.
+        Self {
+            runtime,
+            base_module: module,
+            scripts: DyonScripts::new(directory),
+            handler_failures: HashMap::new(),
+        }
+    }
+
+    /// Runs a snippet of Dyon with a `fn main()` in it against the world,
+    /// with all of the game's library functions available to it.
+    pub fn eval(&mut self, world: &mut specs::World, source: &str) -> Result<(), String> {
+        let module = self.compile_snippet(source)?;
+        self.run_main(world, &module)
+    }
+
+    /// Evaluates a line from the dev ui's REPL, logging what it comes out to to the console,
+    /// or whatever went wrong.
+    pub fn repl(&mut self, world: &mut specs::World, line: &str) {
+        world
+            .write_resource::<DyonConsole>()
+            .0
+            .push_str(&format!("> {}\n", line));
+
+        // expressions get their value shown, anything else is just run.
+        let show = if returns_entities(line) {
+            "repl_entities"
+        } else {
+            "repl_result"
+        };
+        let result = self
+            .compile_snippet(&format!("fn main() {{\n    {}({})\n}}", show, line))
+            .or_else(|_| self.compile_snippet(&format!("fn main() {{\n{}\n}}", line)))
+            .and_then(|module| self.run_main(world, &module));
+
+        if let Err(e) = result {
+            world
+                .write_resource::<DyonConsole>()
+                .0
+                .push_str(&format!(" --- ERROR --- \n{}\n\n", e));
+        }
+    }
+
+    fn compile_snippet(&self, source: &str) -> Result<Arc<dyon::Module>, String> {
+        use crate::prelude::add_budget_checks;
+
+        // a `loop {}` typed into the console shouldn't hang the game either.
+        let mut module = self.base_module.clone();
+        dyon::load_str("snippet", Arc::new(add_budget_checks(source)), &mut module)?;
+        Ok(Arc::new(module))
+    }
+
+    // runs a snippet's main, with everything the library functions need.
+    fn run_main(
+        &mut self,
+        world: &mut specs::World,
+        module: &Arc<dyon::Module>,
+    ) -> Result<(), String> {
+        use current::Current;
+        use dyon::Call;
+
+        let world_guard = CurrentGuard::new(world);
+        let result = {
+            let world = unsafe { &*Current::<specs::World>::new() };
+            let mut dyon_data = world.write_resource::<DyonData>();
+            let dyon_data_guard = CurrentGuard::new(&mut *dyon_data);
+
+            HANDLER_DEADLINE.with(|deadline| deadline.set(Some(Instant::now() + HANDLER_BUDGET)));
+            let result = Call::new("main").run(&mut self.runtime, module);
+            HANDLER_DEADLINE.with(|deadline| deadline.set(None));
+
+            drop(dyon_data_guard);
+            result
+        };
+        drop(world_guard);
+
+        // the snippet can add things lazily too
+        world.maintain();
+
+        result
+    }
+
+    pub fn run(&mut self) {
+        use crate::prelude::*;
+        use current::Current;
+        use dyon::Call;
+        use specs::Join;
+
+        // this happens even when nothing's going to be run, so syntax errors show up right away.
+        {
+            let world = unsafe { &*Current::<specs::World>::new() };
+            let reloaded = self.scripts.reload(
+                &self.base_module,
+                &mut world.write_resource::<DyonConsole>(),
+                &mut world.write_resource::<ScriptHandlers>(),
+            );
+
+            // they might've been fixed, so they all get another chance.
+            if reloaded {
+                self.handler_failures.clear();
+            }
+        }
+
+        // I'm fairly sure this world has to be dropped
+        let mut script_calls = {
+            let world = unsafe { &*Current::<specs::World>::new() };
+            let markers = world.read_storage::<U64Marker>();
+            let mut event_storage = world.write_storage::<ScriptEvent>();
+            let mut queued = world.write_resource::<ScriptEvents>();
+            // ScriptEvent components still work, for the ones put on entities by hand.
+            let placed = (event_storage.drain(), &markers)
+                .join()
+                .map(|(e, x)| (x.id(), e))
+                .collect::<Vec<_>>();
+            // whatever was queued up for an entity that's gone since can't be run.
+            let alive = |id: u64| entity_from_id(world, id).is_some();
+            placed
+                .into_iter()
+                .chain(queued.0.drain(..).filter(|(id, _)| alive(*id)))
+                // events without a handler, like from an Interactable that's just an item,
+                // don't have anything to run.
+                .filter(|(_, e)| !e.function.is_empty())
+                .map(|(id, e)| ScriptCall {
+                    function: e.function,
+                    id,
+                    arg: payload_to_variable(&e.payload),
+                })
+                // copy so no references to the world remain!
+                .collect::<Vec<_>>()
+        };
+
+        // then the entities that want to be updated every so often get their turn.
+        {
+            let world = unsafe { &*Current::<specs::World>::new() };
+            let markers = world.read_storage::<U64Marker>();
+            let mut ticks = world.write_storage::<ScriptTick>();
+            script_calls.extend((&mut ticks, &markers).join().filter_map(|(tick, x)| {
+                tick.elapsed += TICK_SECONDS;
+                (!tick.function.is_empty() && tick.elapsed * 1000.0 >= tick.interval_ms)
+                    .as_option()?;
+
+                // they get told how long it's been since the last time they were called.
+                let delta = std::mem::replace(&mut tick.elapsed, 0.0);
+                Some(ScriptCall {
+                    function: tick.function.clone(),
+                    id: x.id(),
+                    arg: Variable::F64(delta as f64, None),
+                })
+            }));
+        }
+
+        // if there's actually at least one script to bother running...
+        if !script_calls.is_empty() {
+            // open up the DyonData for access by the scripts.
+            // scripts don't get at it through the world, so holding onto it here is fine.
+            let world = unsafe { &*Current::<specs::World>::new() };
+            let mut dyon_data = world.write_resource::<DyonData>();
+            let dyon_data_guard = CurrentGuard::new(&mut *dyon_data);
+
+            // call each handler, and collect the errors
+            let mut output = String::new();
+            for ScriptCall { function, id, arg } in script_calls.iter() {
+                // handlers that keep failing are left alone until they're fixed.
+                if self.handler_failures.get(function).cloned().unwrap_or(0) >= MAX_HANDLER_FAILURES
+                {
+                    continue;
+                }
+
+                let result = match self.scripts.resolve(function) {
+                    Ok((module, name)) => {
+                        let started = Instant::now();
+                        HANDLER_DEADLINE
+                            .with(|deadline| deadline.set(Some(started + HANDLER_BUDGET)));
+                        let result = Call::new(name)
+                            .arg(*id)
+                            .arg(arg.clone())
+                            .run(&mut self.runtime, &module);
+                        HANDLER_DEADLINE.with(|deadline| deadline.set(None));
+
+                        // it could've gone over after the last time it called into the game.
+                        result.and_then(|()| {
+                            (started.elapsed() <= HANDLER_BUDGET).ok_or_else(|| {
+                                format!(
+                                    "Took {}ms, more than its budget of {}ms.",
+                                    started.elapsed().as_millis(),
+                                    HANDLER_BUDGET.as_millis()
+                                )
+                            })
+                        })
+                    }
+                    Err(err) => Err(err),
+                };
+
+                match result {
+                    Ok(()) => {
+                        self.handler_failures.remove(function);
+                    }
+                    Err(err) => {
+                        output
+                            .push_str(&format!(" --- ERROR --- \nfn {}: \n {}\n\n", function, err));
+
+                        let failures = self.handler_failures.entry(function.clone()).or_insert(0);
+                        *failures += 1;
+                        if *failures == MAX_HANDLER_FAILURES {
+                            output.push_str(&format!(
+                                " --- DISABLED --- \nfn {} failed {} times in a row, \
+                                 it won't be run again until the scripts are reloaded.\n\n",
+                                function, MAX_HANDLER_FAILURES
+                            ));
+                        }
+                    }
+                }
+            }
+
+            // now that all of the modules are done accessing it,
+            drop(dyon_data_guard);
+            drop(dyon_data);
+
+            // quickly add the errors that could've been outputted to the console
+            let mut dyon_console = world.write_resource::<DyonConsole>();
+            dyon_console.0.push_str(&output);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::prelude::*;
+    use specs::{saveload::MarkedBuilder, Builder, Join};
+
+    // builds something from types.json, and steps the world once so it has a body.
+    fn spawn(headless: &mut Headless, assemblage: &str) -> u64 {
+        let ent = {
+            let world = &headless.world;
+            world.read_resource::<Assemblager>().build(
+                assemblage,
+                &world.read_resource(),
+                &world.entities(),
+            )
+        };
+        headless.world.maintain();
+        headless.step(UserInput::default());
+
+        id_of_entity(&headless.world, ent).unwrap()
+    }
+
+    // something with just health, that physics won't bother.
+    fn spawn_with_health(headless: &mut Headless, value: f32, max: f32) -> u64 {
+        let ent = headless
+            .world
+            .create_entity()
+            .with(Health { value, max })
+            .marked::<U64Marker>()
+            .build();
+
+        id_of_entity(&headless.world, ent).unwrap()
+    }
+
+    fn eval(headless: &mut Headless, body: String) {
+        let source = format!("fn main() {{\n{}\n}}", body);
+        if let Err(e) = headless.eval(&source) {
+            panic!("{}\n\nin:\n{}", e, source);
+        }
+    }
+
+    fn health(headless: &Headless, id: u64) -> Health {
+        let ent = entity_from_id(&headless.world, id).unwrap();
+        headless
+            .world
+            .read_storage::<Health>()
+            .get(ent)
+            .unwrap()
+            .clone()
+    }
+
+    fn location(headless: &Headless, id: u64) -> glm::TVec3<f32> {
+        let ent = entity_from_id(&headless.world, id).unwrap();
+        let physes = headless.world.read_storage::<Phys>();
+        *headless
+            .world
+            .read_resource::<PhysState>()
+            .location(physes.get(ent).unwrap())
+            .unwrap()
+    }
+
+    #[test]
+    fn set_hp() {
+        let mut headless = Headless::new();
+        let id = spawn_with_health(&mut headless, 50.0, 100.0);
+
+        eval(&mut headless, format!("set_hp_entity({}, 20)", id));
+        assert_eq!(health(&headless, id).value, 20.0);
+
+        // it can't go over the max
+        eval(&mut headless, format!("set_hp_entity({}, 150)", id));
+        assert_eq!(health(&headless, id).value, 100.0);
+    }
+
+    #[test]
+    fn set_hp_percent() {
+        let mut headless = Headless::new();
+        let id = spawn_with_health(&mut headless, 50.0, 200.0);
+
+        eval(&mut headless, format!("set_hp_percent_entity({}, 25)", id));
+        assert_eq!(health(&headless, id).value, 50.0);
+
+        eval(&mut headless, format!("set_hp_percent_entity({}, 300)", id));
+        assert_eq!(health(&headless, id).value, 200.0);
+    }
+
+    #[test]
+    fn change_hp() {
+        let mut headless = Headless::new();
+        let id = spawn_with_health(&mut headless, 50.0, 100.0);
+
+        eval(&mut headless, format!("change_hp_entity({}, 10)", id));
+        assert_eq!(health(&headless, id).value, 60.0);
+
+        eval(&mut headless, format!("change_hp_entity({}, -15)", id));
+        assert_eq!(health(&headless, id).value, 45.0);
+
+        eval(&mut headless, format!("change_hp_entity({}, 1000)", id));
+        assert_eq!(health(&headless, id).value, 100.0);
+    }
+
+    #[test]
+    fn change_hp_percent() {
+        let mut headless = Headless::new();
+        let id = spawn_with_health(&mut headless, 40.0, 100.0);
+
+        // a percent of the current health, not the max
+        eval(
+            &mut headless,
+            format!("change_hp_percent_entity({}, 50)", id),
+        );
+        assert_eq!(health(&headless, id).value, 60.0);
+
+        eval(
+            &mut headless,
+            format!("change_hp_percent_entity({}, -50)", id),
+        );
+        assert_eq!(health(&headless, id).value, 30.0);
+
+        eval(
+            &mut headless,
+            format!("change_hp_percent_entity({}, 1000)", id),
+        );
+        assert_eq!(health(&headless, id).value, 100.0);
+    }
+
+    #[test]
+    fn damage() {
+        let mut headless = Headless::new();
+        let ent = headless
+            .world
+            .create_entity()
+            .with(Health {
+                value: 50.0,
+                max: 100.0,
+            })
+            .with(Resistances {
+                multipliers: vec![("fire".to_owned(), 0.5)].into_iter().collect(),
+            })
+            .with(Invulnerability {
+                seconds: 1.0,
+                remaining: 0.0,
+            })
+            .with(OnDeath::default())
+            .marked::<U64Marker>()
+            .build();
+        let id = id_of_entity(&headless.world, ent).unwrap();
+
+        // nothing happens until the next tick
+        eval(
+            &mut headless,
+            format!("damage_entity({}, 20, \"fire\")", id),
+        );
+        assert_eq!(health(&headless, id).value, 50.0);
+        headless.step(UserInput::default());
+        assert_eq!(health(&headless, id).value, 40.0);
+
+        // still invulnerable from the last hit, but healing always works
+        eval(
+            &mut headless,
+            format!(
+                "damage_entity({0}, 20, \"physical\")
+                 damage_entity({0}, -5, \"physical\")",
+                id
+            ),
+        );
+        headless.step(UserInput::default());
+        assert_eq!(health(&headless, id).value, 45.0);
+
+        for _ in 0..(1.0 / TICK_SECONDS) as usize {
+            headless.step(UserInput::default());
+        }
+        eval(
+            &mut headless,
+            format!("damage_entity_by({}, 7, 100, \"physical\")", id),
+        );
+        headless.step(UserInput::default());
+        assert_eq!(health(&headless, id).value, 0.0);
+        let on_death = headless
+            .world
+            .read_storage::<OnDeath>()
+            .get(ent)
+            .unwrap()
+            .clone();
+        assert_eq!(on_death.state, DeathState::Dying);
+        assert_eq!(on_death.killer, Some(7));
+    }
+
+    #[test]
+    fn attacks() {
+        let mut headless = Headless::new();
+        let attacker = spawn(&mut headless, "business chicken");
+        let target = spawn(&mut headless, "business chicken");
+        let target_ent = entity_from_id(&headless.world, target).unwrap();
+        headless
+            .world
+            .write_storage::<Health>()
+            .insert(
+                target_ent,
+                Health {
+                    value: 10.0,
+                    max: 10.0,
+                },
+            )
+            .unwrap();
+
+        // the hammer reaches 0.9, so they're close enough but not touching.
+        eval(
+            &mut headless,
+            format!(
+                "teleport_entity({0}, (20, 20, 0.5))
+                 teleport_entity({1}, (20.6, 20, 0.5))
+                 equip_entity({0}, \"hammer\")
+                 attack_entity({0}, (1, 0, 0))",
+                attacker, target
+            ),
+        );
+        headless.step(UserInput::default());
+        assert_eq!(health(&headless, target).value, 8.0);
+        assert!(headless
+            .world
+            .read_storage::<ApplyForce>()
+            .get(target_ent)
+            .is_some());
+
+        // still cooling down
+        eval(
+            &mut headless,
+            format!("attack_entity({}, (1, 0, 0))", attacker),
+        );
+        headless.step(UserInput::default());
+        assert_eq!(health(&headless, target).value, 8.0);
+
+        // swinging the other way misses
+        for _ in 0..(1.0 / TICK_SECONDS) as usize {
+            headless.step(UserInput::default());
+        }
+        eval(
+            &mut headless,
+            format!(
+                "teleport_entity({0}, (20, 20, 0.5))
+                 teleport_entity({1}, (20.6, 20, 0.5))
+                 attack_entity({0}, (-1, 0, 0))",
+                attacker, target
+            ),
+        );
+        headless.step(UserInput::default());
+        assert_eq!(health(&headless, target).value, 8.0);
+
+        assert!(headless
+            .eval(&format!(
+                "fn main() {{ equip_entity({}, \"not a weapon\") }}",
+                attacker
+            ))
+            .is_err());
+    }
+
+    #[test]
+    fn ai_states() {
+        let mut headless = Headless::new();
+        let npc = spawn(&mut headless, "business chicken");
+        let ent = entity_from_id(&headless.world, npc).unwrap();
+        let state = |name: &str, behaviour, transitions| AiState {
+            name: name.to_owned(),
+            behaviour,
+            speed: 3.0,
+            on_enter: String::new(),
+            transitions,
+        };
+        headless
+            .world
+            .write_storage::<AiBehaviour>()
+            .insert(
+                ent,
+                AiBehaviour {
+                    initial: "rest".to_owned(),
+                    states: vec![
+                        state(
+                            "rest",
+                            Behaviour::Idle,
+                            vec![Transition {
+                                when: Condition::After(0.5),
+                                to: "leave".to_owned(),
+                            }],
+                        ),
+                        state(
+                            "leave",
+                            Behaviour::Flee {
+                                safe_distance: 100.0,
+                            },
+                            vec![Transition {
+                                when: Condition::AwayFromHome(1.0),
+                                to: "go home".to_owned(),
+                            }],
+                        ),
+                        state(
+                            "go home",
+                            Behaviour::ReturnHome,
+                            vec![Transition {
+                                when: Condition::AtHome,
+                                to: "rest".to_owned(),
+                            }],
+                        ),
+                    ],
+                    ..AiBehaviour::default()
+                },
+            )
+            .unwrap();
+
+        headless.step(UserInput::default());
+        eval(&mut headless, format!("log(ai_state_entity({}))", npc));
+        assert_eq!(headless.take_console(), "rest\n");
+
+        // it rests, runs from the player, then comes back and rests again.
+        let mut seen = vec!["rest".to_owned()];
+        for _ in 0..(10.0 / TICK_SECONDS) as usize {
+            headless.step(UserInput::default());
+            let current = headless
+                .world
+                .read_storage::<AiBehaviour>()
+                .get(ent)
+                .unwrap()
+                .current
+                .clone()
+                .unwrap();
+            if seen.last() != Some(&current) {
+                seen.push(current);
+            }
+        }
+        assert_eq!(&seen[..4], &["rest", "leave", "go home", "rest"]);
+
+        eval(
+            &mut headless,
+            format!(
+                "set_ai_state_entity({0}, \"go home\")
+                 log(ai_state_entity({0}))",
+                npc
+            ),
+        );
+        assert_eq!(headless.take_console(), "go home\n");
+        assert!(headless
+            .eval(&format!(
+                "fn main() {{ set_ai_state_entity({}, \"not a state\") }}",
+                npc
+            ))
+            .is_err());
+    }
+
+    #[test]
+    fn hp_of_entity_without_health() {
+        let mut headless = Headless::new();
+        let id = spawn(&mut headless, "small tree");
+
+        assert!(headless
+            .eval(&format!("fn main() {{ change_hp_entity({}, 10) }}", id))
+            .is_err());
+    }
+
+    #[test]
+    fn teleport_and_position() {
+        let mut headless = Headless::new();
+        let id = spawn(&mut headless, "small tree");
+
+        eval(
+            &mut headless,
+            format!(
+                "teleport_entity({0}, (3, 4, 5))
+                 pos := position_entity({0})
+                 log(str(x(pos)) + \",\" + str(y(pos)) + \",\" + str(z(pos)))",
+                id
+            ),
+        );
+        assert_eq!(location(&headless, id), glm::vec3(3.0, 4.0, 5.0));
+        assert_eq!(headless.take_console(), "3,4,5\n");
+    }
+
+    #[test]
+    fn velocity() {
+        let mut headless = Headless::new();
+        let id = spawn(&mut headless, "business chicken");
+
+        eval(
+            &mut headless,
+            format!(
+                "set_velocity_entity({0}, (1, 2, 0))
+                 vel := velocity_entity({0})
+                 log(str(x(vel)) + \",\" + str(y(vel)))",
+                id
+            ),
+        );
+        assert_eq!(headless.take_console(), "1,2\n");
+    }
+
+    #[test]
+    fn apply_force() {
+        let mut headless = Headless::new();
+        let id = spawn(&mut headless, "business chicken");
+
+        eval(
+            &mut headless,
+            format!("apply_force_entity({}, (0, 5, 0), 0.5)", id),
+        );
+
+        let ent = entity_from_id(&headless.world, id).unwrap();
+        let forces = headless.world.read_storage::<ApplyForce>();
+        let force = forces.get(ent).unwrap();
+        assert_eq!(force.vec, glm::vec3(0.0, 5.0, 0.0));
+        assert_eq!(force.duration, 0.5);
+    }
+
+    #[test]
+    fn images() {
+        let mut headless = Headless::new();
+        let id = spawn(&mut headless, "small tree");
+
+        eval(
+            &mut headless,
+            format!(
+                "set_image_entity({0}, \"broccoli\")
+                 log(image_entity({0}))",
+                id
+            ),
+        );
+        assert_eq!(headless.take_console(), "broccoli\n");
+
+        let ent = entity_from_id(&headless.world, id).unwrap();
+        let builders = headless.world.read_storage::<AppearanceBuilder>();
+        assert!(!builders.get(ent).unwrap().built);
+
+        // only images that are actually on the spritesheet
+        drop(builders);
+        assert!(headless
+            .eval(&format!(
+                "fn main() {{ set_image_entity({}, \"not an image\") }}",
+                id
+            ))
+            .is_err());
+    }
+
+    #[test]
+    fn set_animation() {
+        let mut headless = Headless::new();
+        let id = spawn(&mut headless, "business chicken");
+
+        eval(
+            &mut headless,
+            format!("set_animation_entity({}, 3, 12)", id),
+        );
+
+        let ent = entity_from_id(&headless.world, id).unwrap();
+        let animations = headless.world.read_storage::<Animation>();
+        let animation = animations.get(ent).unwrap();
+        assert_eq!(animation.frame_count, 3);
+        assert_eq!(animation.fps, 12.0);
+        drop(animations);
+
+        for (frame_count, fps) in &[("0", "12"), ("-2", "12"), ("3", "1/0")] {
+            assert!(headless
+                .eval(&format!(
+                    "fn main() {{ set_animation_entity({}, {}, {}) }}",
+                    id, frame_count, fps
+                ))
+                .is_err());
+        }
+    }
+
+    #[test]
+    fn add_and_remove_components() {
+        let mut headless = Headless::new();
+        let id = spawn(&mut headless, "small tree");
+
+        // fields that aren't given are left as their defaults
+        eval(
+            &mut headless,
+            format!("add_component_entity({}, \"Health\", {{max: 10}})", id),
+        );
+        assert_eq!(health(&headless, id).max, 10.0);
+        assert_eq!(health(&headless, id).value, 0.0);
+
+        eval(
+            &mut headless,
+            format!("remove_component_entity({}, \"Health\")", id),
+        );
+        let ent = entity_from_id(&headless.world, id).unwrap();
+        assert!(headless.world.read_storage::<Health>().get(ent).is_none());
+
+        assert!(headless
+            .eval(&format!(
+                "fn main() {{ add_component_entity({}, \"NotAComponent\", {{}}) }}",
+                id
+            ))
+            .is_err());
+    }
+
+    #[test]
+    fn scripting_ids() {
+        let mut headless = Headless::new();
+        let one = spawn(&mut headless, "melo");
+        let two = spawn(&mut headless, "small tree");
+
+        eval(
+            &mut headless,
+            format!(
+                "add_id_entity({0}, \"test thing\")
+                 add_id_entity({1}, \"test thing\")
+                 log(str(has_id_entity({0}, \"test thing\")))
+                 log(str(has_id_entity({1}, \"something else\")))
+                 log(str(len(all_with_id(\"test thing\"))))",
+                one, two
+            ),
+        );
+        assert_eq!(headless.take_console(), "true\nfalse\n2\n");
+    }
+
+    #[test]
+    fn caches() {
+        let mut headless = Headless::new();
+        let id = spawn(&mut headless, "business chicken");
+
+        eval(
+            &mut headless,
+            format!(
+                "cache := get_cache_entity({})
+                 cache.visits := 3
+                 save_caches()",
+                id
+            ),
+        );
+        eval(
+            &mut headless,
+            format!("log(str(get_cache_entity({}).visits))", id),
+        );
+        assert_eq!(headless.take_console(), "3\n");
+
+        let saved = headless
+            .world
+            .read_resource::<DyonData>()
+            .save_caches(|saved_id| saved_id == id);
+        assert_eq!(saved[&id]["visits"], 3.0);
+    }
+
+    #[test]
+    fn caches_go_with_their_entity() {
+        let mut headless = Headless::new();
+        let id = spawn_with_health(&mut headless, 1.0, 1.0);
+
+        eval(
+            &mut headless,
+            format!(
+                "cache := get_cache_entity({})
+                 cache.visits := 3
+                 save_caches()",
+                id
+            ),
+        );
+        assert!(headless
+            .world
+            .read_resource::<DyonData>()
+            .cache_value(id, "visits")
+            .is_some());
+
+        // deleted by something other than a script, like dying is.
+        let ent = entity_from_id(&headless.world, id).unwrap();
+        headless.world.delete_entity(ent).unwrap();
+        headless.step(UserInput::default());
+
+        assert!(headless
+            .world
+            .read_resource::<DyonData>()
+            .cache_value(id, "visits")
+            .is_none());
+    }
+
+    #[test]
+    fn spawn_and_delete() {
+        let mut headless = Headless::new();
+        let spot = spawn(&mut headless, "small tree");
+
+        eval(
+            &mut headless,
+            format!(
+                "melo := spawn_at_entity({}, \"melo\")
+                 log(str(melo))",
+                spot
+            ),
+        );
+        let melo: u64 = headless.take_console().trim().parse().unwrap();
+        let melo_ent = entity_from_id(&headless.world, melo).unwrap();
+        assert_eq!(
+            headless
+                .world
+                .read_storage::<Assemblaged>()
+                .get(melo_ent)
+                .unwrap()
+                .built_from,
+            "melo"
+        );
+
+        eval(&mut headless, format!("delete_entity({})", melo));
+        assert!(entity_from_id(&headless.world, melo).is_none());
+
+        // there's no type by that name
+        assert!(headless
+            .eval(&format!(
+                "fn main() {{ spawn_at_entity({}, \"not a type\") }}",
+                spot
+            ))
+            .is_err());
+    }
+
+    #[test]
+    fn timers() {
+        let mut headless = Headless::new();
+        let id = spawn(&mut headless, "small tree");
+
+        eval(
+            &mut headless,
+            format!(
+                "once := after(1, \"ring\", {0})
+                 again := every(0.5, \"ring\", {0})
+                 log(str(cancel(once)))
+                 log(str(cancel(once)))",
+                id
+            ),
+        );
+        assert_eq!(headless.take_console(), "true\nfalse\n");
+
+        // the one that's left goes off every half a second
+        let mut scheduler = headless.world.write_resource::<ScriptScheduler>();
+        assert!(scheduler.advance(0.25).is_empty());
+        let fired = scheduler.advance(0.25);
+        assert_eq!(fired.len(), 1);
+        assert_eq!(fired[0].1.entity, id);
+        assert_eq!(scheduler.advance(0.5).len(), 1);
+    }
+
+    #[test]
+    fn items() {
+        let mut headless = Headless::new();
+        let ent = headless
+            .world
+            .create_entity()
+            .with(Inventory {
+                slots: vec![None; 2],
+            })
+            .marked::<U64Marker>()
+            .build();
+        let id = id_of_entity(&headless.world, ent).unwrap();
+
+        // melons stack up to 5, so only 10 fit
+        eval(
+            &mut headless,
+            format!(
+                "log(str(give_item_entity({0}, \"melon\", 7)))
+                 log(str(give_item_entity({0}, \"melon\", 7)))
+                 log(str(item_count_entity({0}, \"melon\")))
+                 log(str(take_item_entity({0}, \"melon\", 11)))
+                 log(str(take_item_entity({0}, \"melon\", 4)))
+                 log(str(inventory_entity({0}).melon))",
+                id
+            ),
+        );
+        assert_eq!(headless.take_console(), "0\n4\n10\nfalse\ntrue\n6\n");
+
+        // there's no item by that name
+        assert!(headless
+            .eval(&format!(
+                "fn main() {{ give_item_entity({}, \"not an item\", 1) }}",
+                id
+            ))
+            .is_err());
+    }
+
+    #[test]
+    fn quests() {
+        let mut headless = Headless::new();
+
+        eval(
+            &mut headless,
+            "log(str(start_quest(\"melon delivery\")))
+             log(str(start_quest(\"melon delivery\")))
+             log(quest_status(\"melon delivery\"))
+             log(str(quest_stage(\"melon delivery\")))
+             log(str(advance_quest(\"melon delivery\")))
+             log(str(quest_stage(\"melon delivery\")))"
+                .to_owned(),
+        );
+        assert_eq!(headless.take_console(), "true\nfalse\nactive\n0\ntrue\n1\n");
+
+        // the player's hurt, so the reward can be seen
+        let player = {
+            let world = &headless.world;
+            let (_, ent) = (&world.read_storage::<MovementControls>(), &world.entities())
+                .join()
+                .next()
+                .unwrap();
+            world.write_storage::<Health>().get_mut(ent).unwrap().value = 1.0;
+            id_of_entity(world, ent).unwrap()
+        };
+
+        eval(
+            &mut headless,
+            "log(str(complete_quest(\"melon delivery\")))
+             log(str(complete_quest(\"melon delivery\")))
+             log(quest_status(\"melon delivery\"))
+             log(str(quest_stage(\"melon delivery\")))"
+                .to_owned(),
+        );
+        assert_eq!(headless.take_console(), "true\nfalse\ncomplete\n-1\n");
+
+        // the rewards are handed out on the next tick
+        headless.step(UserInput::default());
+        assert_eq!(health(&headless, player).value, 6.0);
+        assert!(headless.take_console().contains("It's over, boys."));
+
+        // there's no quest by that name
+        assert!(headless
+            .eval("fn main() { start_quest(\"not a quest\") }")
+            .is_err());
+    }
+
+    #[test]
+    fn log() {
+        let mut headless = Headless::new();
+
+        eval(&mut headless, "log(\"hello\")\nlog(\"there\")".to_owned());
+        assert_eq!(headless.take_console(), "hello\nthere\n");
+    }
+
+    #[test]
+    fn scripts_compile_with_budget_checks() {
+        let mut state = DyonState::new();
+        let mut console = DyonConsole::default();
+        let mut handlers = ScriptHandlers::default();
+
+        assert!(state
+            .scripts
+            .reload(&state.base_module, &mut console, &mut handlers));
+        assert_eq!(console.0, "");
+        assert!(handlers
+            .0
+            .contains(&"business_chicken::business_greeting".to_owned()));
+    }
+
+    #[test]
+    fn budget_checks_leave_values_alone() {
+        let mut headless = Headless::new();
+        let source = "
+fn empty() -> {} {
+    x := 1
+    {}
+}
+
+fn main() {
+    add := \\(x) = {
+        y := x + 1
+        y
+    }
+    log(str(\\add(1)))
+    log(str(if true { 1 } else { 2 }))
+    log(typeof(empty()))
+    for i 2 { log(str(i)) }
+}";
+
+        if let Err(e) = headless.eval(source) {
+            panic!("{}", e);
+        }
+        assert_eq!(headless.take_console(), "2\n1\nobject\n0\n1\n");
+    }
+
+    #[test]
+    fn snippets_have_a_budget_too() {
+        let mut headless = Headless::new();
+
+        let err = headless.eval("fn main() { loop {} }").unwrap_err();
+        assert!(err.contains("Took longer than"), "{}", err);
+
+        // the REPL reports it, and is still usable after.
+        let mut dyon_state = DyonState::new();
+        dyon_state.repl(&mut headless.world, "loop {}");
+        dyon_state.repl(&mut headless.world, "1 + 0.5");
+        let console = headless.take_console();
+        assert!(console.contains(" --- ERROR --- "), "{}", console);
+        assert!(console.ends_with("> 1 + 0.5\n1.5\n"), "{}", console);
+    }
+
+    #[test]
+    fn repl_only_links_entities() {
+        let mut headless = Headless::new();
+        let id = spawn(&mut headless, "small tree");
+
+        let mut dyon_state = DyonState::new();
+        dyon_state.repl(&mut headless.world, &format!("{} + 0", id));
+        dyon_state.repl(&mut headless.world, "all_with_id(\"business chicken\")");
+        let console = headless.take_console();
+
+        let lines = console.lines().collect::<Vec<_>>();
+        assert_eq!(lines[1], id.to_string());
+        assert!(lines[3].starts_with("[#"), "{}", console);
+    }
+}
@@ -0,0 +1,66 @@
+use imgui::ImString;
+
+/// What's been typed into the Dyon console in the dev ui.
+/// The dev ui can't get at the DyonState, so lines are left in `pending` for the main loop to run.
+pub struct DyonRepl {
+    pub input: ImString,
+    pub history: Vec<String>,
+    // where they are in the history while they're going through it with the arrow keys,
+    // None when they're typing in something new.
+    pub history_index: Option<usize>,
+    pub pending: Option<String>,
+}
+
+impl DyonRepl {
+    pub fn new() -> Self {
+        Self {
+            input: ImString::with_capacity(256),
+            history: Vec::new(),
+            history_index: None,
+            pending: None,
+        }
+    }
+
+    /// Hands off whatever's been typed in to be run.
+    pub fn submit(&mut self) {
+        let line = self.input.to_str().trim().to_owned();
+        self.input.clear();
+        self.history_index = None;
+        if line.is_empty() {
+            return;
+        }
+
+        // running the same thing a bunch of times shouldn't fill up the history.
+        if self.history.last() != Some(&line) {
+            self.history.push(line.clone());
+        }
+        self.pending = Some(line);
+    }
+
+    fn show_history(&mut self, index: Option<usize>) {
+        self.history_index = index;
+        self.input.clear();
+        if let Some(line) = index.and_then(|i| self.history.get(i)) {
+            self.input.push_str(line);
+        }
+    }
+
+    /// Goes back to the line before the one in the input.
+    pub fn previous(&mut self) {
+        let index = match self.history_index {
+            Some(index) => Some(index.saturating_sub(1)),
+            None => self.history.len().checked_sub(1),
+        };
+        if index.is_some() {
+            self.show_history(index);
+        }
+    }
+
+    /// Goes forward to the line after the one in the input, or an empty line after the last one.
+    pub fn next(&mut self) {
+        match self.history_index {
+            Some(index) if index + 1 < self.history.len() => self.show_history(Some(index + 1)),
+            _ => self.show_history(None),
+        }
+    }
+}
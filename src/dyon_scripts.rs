@@ -0,0 +1,403 @@
+use crate::prelude::*;
+use dyon::Module;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+// every .dyon file in here is its own module, named after the file.
+// modules can call each other's functions, each one's built on top of whichever others it needs.
+pub const SCRIPTS_DIRECTORY: &str = "src/dyon";
+/// The library function that every block in a script calls before anything else,
+/// so a handler that's gone over its budget can be stopped even if it never calls into the game.
+pub const BUDGET_CHECK: &str = "check_budget";
+
+/// The handlers that scripts define, as `module::function`.
+/// It's a resource so the dev ui can offer them up when picking an event handler.
+#[derive(Default)]
+pub struct ScriptHandlers(pub Vec<String>);
+
+fn is_word(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || byte == b'_' || byte >= 0x80
+}
+
+// where the string starting at `i` ends.
+fn skip_string(bytes: &[u8], mut i: usize) -> usize {
+    i += 1;
+    while i < bytes.len() && bytes[i] != b'"' {
+        i += if bytes[i] == b'\\' { 2 } else { 1 };
+    }
+    i + 1
+}
+
+// where the comment starting at `i` ends, or just `i` if there isn't one there.
+fn skip_comment(bytes: &[u8], mut i: usize) -> usize {
+    match bytes.get(i..i + 2) {
+        Some(b"//") => {
+            while i < bytes.len() && bytes[i] != b'\n' {
+                i += 1;
+            }
+            i
+        }
+        // these can be nested.
+        Some(b"/*") => {
+            let mut depth = 0;
+            while i < bytes.len() {
+                match bytes.get(i..i + 2) {
+                    Some(b"/*") => depth += 1,
+                    Some(b"*/") => depth -= 1,
+                    _ => {
+                        i += 1;
+                        continue;
+                    }
+                }
+                i += 2;
+                if depth == 0 {
+                    break;
+                }
+            }
+            i
+        }
+        _ => i,
+    }
+}
+
+// the next thing at or after `i` that isn't whitespace or a comment,
+// and whether or not there was a new line before it.
+fn next_token(bytes: &[u8], mut i: usize) -> (usize, bool) {
+    let mut new_line = false;
+    while i < bytes.len() {
+        if bytes[i].is_ascii_whitespace() {
+            new_line |= bytes[i] == b'\n';
+            i += 1;
+        } else {
+            let end = skip_comment(bytes, i);
+            if end == i {
+                break;
+            }
+            i = end;
+        }
+    }
+    (i, new_line)
+}
+
+/// Has every block in a script start off by calling `BUDGET_CHECK`, so a handler stuck in a loop
+/// gets stopped once it's out of time. The call goes on the same line as the `{` wherever the
+/// block doesn't have anything else on that line, so errors nearly always point at the right line.
+/// Blocks that do, like `loop { x += 1 }`, push everything after them a line further down.
+pub fn add_budget_checks(source: &str) -> String {
+    let bytes = source.as_bytes();
+    let mut instrumented = String::with_capacity(source.len());
+    let mut copied = 0;
+    // whatever came right before, to tell blocks apart from objects.
+    let mut last_byte = b'\n';
+    let mut last_word = "";
+    // whether that was on an earlier line.
+    let mut after_new_line = true;
+
+    let mut i = 0;
+    while i < bytes.len() {
+        let byte = bytes[i];
+        let start = i;
+        let comment_end = skip_comment(bytes, i);
+        if byte.is_ascii_whitespace() {
+            after_new_line |= byte == b'\n';
+            i += 1;
+            continue;
+        } else if comment_end != i {
+            i = comment_end;
+            continue;
+        } else if is_word(byte) {
+            while i < bytes.len() && is_word(bytes[i]) {
+                i += 1;
+            }
+            last_word = &source[start..i];
+            last_byte = bytes[i - 1];
+            after_new_line = false;
+            continue;
+        } else if byte == b'"' {
+            i = skip_string(bytes, i);
+        } else {
+            i += 1;
+        }
+
+        if byte == b'{' {
+            let (next, new_line) = next_token(bytes, i);
+            let empty = bytes.get(next) == Some(&b'}');
+
+            // `{a: 1}` and `{"a": 1}` are objects,
+            let key_end = match bytes.get(next) {
+                Some(b'"') => Some(skip_string(bytes, next)),
+                Some(&b) if is_word(b) => Some(
+                    (next..bytes.len())
+                        .find(|&j| !is_word(bytes[j]))
+                        .unwrap_or(bytes.len()),
+                ),
+                _ => None,
+            };
+            let keyed = key_end.map_or(false, |end| {
+                let (colon, _) = next_token(bytes, end);
+                bytes.get(colon) == Some(&b':') && bytes.get(colon + 1) != Some(&b'=')
+            });
+            // and so is `{}`, unless it's coming after something like `loop`, `else` or `fn f()`
+            // on the same line. on a line of its own, it's a value, like a function returning it.
+            let after_header = (is_word(last_byte) || b")]}".contains(&last_byte))
+                && last_word != "return"
+                && !after_new_line;
+            // links are just a list of things to glue together, not statements.
+            let link = last_word == "link";
+
+            if !keyed && !link && (!empty || after_header) {
+                instrumented.push_str(&source[copied..i]);
+                instrumented.push_str(BUDGET_CHECK);
+                instrumented.push_str("()");
+                if !new_line && !empty {
+                    instrumented.push('\n');
+                }
+                copied = i;
+            }
+        }
+
+        last_byte = byte;
+        last_word = "";
+        after_new_line = false;
+    }
+    instrumented.push_str(&source[copied..]);
+    instrumented
+}
+
+/// Keeps the compiled version of every script module around,
+/// rebuilding them when anything in the scripts directory changes.
+pub struct DyonScripts {
+    directory: PathBuf,
+    modules: HashMap<String, Arc<Module>>,
+    // which functions each module defines itself, not counting the ones it imports.
+    functions: HashMap<String, Vec<String>>,
+    watcher: FileWatcher,
+    compiled: bool,
+}
+
+impl DyonScripts {
+    /// Loads scripts from `directory`, which is usually `SCRIPTS_DIRECTORY`.
+    pub fn new<P: Into<PathBuf>>(directory: P) -> Self {
+        let directory = directory.into();
+        let mut watcher = FileWatcher::new();
+        // files being added or removed changes when the directory was modified.
+        watcher.watch(directory.clone());
+
+        Self {
+            directory,
+            modules: HashMap::new(),
+            functions: HashMap::new(),
+            watcher,
+            compiled: false,
+        }
+    }
+
+    fn script_path(&self, module_name: &str) -> PathBuf {
+        self.directory.join(format!("{}.dyon", module_name))
+    }
+
+    /// The names of all of the modules in the scripts directory.
+    fn module_names(&self) -> Vec<String> {
+        let mut names = std::fs::read_dir(&self.directory)
+            .map(|dir| {
+                dir.filter_map(|entry| entry.ok())
+                    .map(|entry| entry.path())
+                    .filter(|path| path.extension().map_or(false, |ext| ext == "dyon"))
+                    .filter_map(|path| Some(path.file_stem()?.to_str()?.to_owned()))
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        names.sort();
+        names
+    }
+
+    /// Compiles the scripts if any of them have changed since last time, or they haven't been
+    /// compiled yet. Any module that doesn't compile keeps the last version of it that did,
+    /// and the errors go to the console. Returns whether or not they were compiled.
+    pub fn reload(
+        &mut self,
+        base_module: &Module,
+        dyon_console: &mut DyonConsole,
+        handlers: &mut ScriptHandlers,
+    ) -> bool {
+        let changed = !self.watcher.changed().is_empty();
+        if self.compiled && !changed {
+            return false;
+        }
+        self.compiled = true;
+
+        let names = self.module_names();
+        let mut sources = HashMap::new();
+        for name in names.iter() {
+            let path = self.script_path(name);
+            self.watcher.watch(path.clone());
+
+            match std::fs::read_to_string(&path) {
+                Ok(source) => {
+                    sources.insert(name.clone(), Arc::new(add_budget_checks(&source)));
+                }
+                Err(e) => dyon_console.0.push_str(&format!(
+                    " --- LOAD ERROR --- \n{}: {}\n\n",
+                    path.display(),
+                    e
+                )),
+            }
+        }
+
+        // modules whose files are gone shouldn't stick around.
+        self.modules.retain(|name, _| sources.contains_key(name));
+        self.functions.retain(|name, _| sources.contains_key(name));
+
+        // a module that calls into another won't compile until that one has, so they're compiled
+        // over and over, each time with every module that's compiled so far imported into them,
+        // until that stops getting any more of them compiled.
+        let mut compiled: Vec<(String, Module)> = Vec::new();
+        let mut pending = names
+            .iter()
+            .filter(|name| sources.contains_key(*name))
+            .collect::<Vec<_>>();
+        let mut errors = HashMap::new();
+        while !pending.is_empty() {
+            let mut still_pending = Vec::new();
+            for name in pending.iter().cloned() {
+                let mut module = base_module.clone();
+                for (_, other) in compiled.iter() {
+                    module.import(other);
+                }
+                let imported = module.functions.len();
+
+                match dyon::load_str(
+                    &self.script_path(name).to_string_lossy(),
+                    Arc::clone(&sources[name]),
+                    &mut module,
+                ) {
+                    Ok(()) => {
+                        // whatever came after the imports is what this module defines itself.
+                        let functions = module.functions[imported..]
+                            .iter()
+                            .map(|function| function.name.to_string())
+                            .collect();
+                        self.functions.insert(name.clone(), functions);
+                        compiled.push((name.clone(), module));
+                        errors.remove(name);
+                    }
+                    Err(e) => {
+                        errors.insert(name.clone(), e);
+                        still_pending.push(name);
+                    }
+                }
+            }
+
+            if still_pending.len() == pending.len() {
+                break;
+            }
+            pending = still_pending;
+        }
+
+        for (name, module) in compiled {
+            if changed {
+                info!("Reloaded script module {}", name);
+            }
+            self.modules.insert(name, Arc::new(module));
+        }
+        // these are almost always syntax errors but I guess they could
+        // be other things actually? whatever I'll just leave it this way.
+        for (_, e) in errors {
+            dyon_console
+                .0
+                .push_str(&format!(" --- SYNTAX ERROR --- \n{}\n\n", e));
+        }
+
+        handlers.0 = self
+            .functions
+            .iter()
+            .flat_map(|(module, functions)| {
+                functions
+                    .iter()
+                    .map(move |function| format!("{}::{}", module, function))
+            })
+            .collect();
+        handlers.0.sort();
+
+        true
+    }
+
+    /// Finds the module that can run a handler, and the function's name inside of that module.
+    /// Handlers are named like `module::function`, but plain function names are still looked
+    /// for in every module, so older content keeps working. If more than one module has a
+    /// function by that name, none of them are picked, the handler has to say which it means.
+    pub fn resolve<'a>(&self, handler: &'a str) -> Result<(Arc<Module>, &'a str), String> {
+        let no_handler = || "there's no script handler by that name.".to_owned();
+        let mut parts = handler.rsplitn(2, "::");
+        let function = parts.next().ok_or_else(no_handler)?;
+
+        let module_name = match parts.next() {
+            Some(module_name) => module_name,
+            None => {
+                let mut defined_in = self
+                    .functions
+                    .iter()
+                    .filter(|(_, functions)| functions.iter().any(|f| f == function))
+                    .map(|(module_name, _)| module_name.as_str())
+                    .collect::<Vec<_>>();
+                defined_in.sort();
+
+                match defined_in.as_slice() {
+                    [] => return Err(no_handler()),
+                    [module_name] => module_name,
+                    _ => {
+                        return Err(format!(
+                            "there's a {} in more than one script ({}), \
+                             name the one it should be like `{}::{}`.",
+                            function,
+                            defined_in.join(", "),
+                            defined_in[0],
+                            function
+                        ))
+                    }
+                }
+            }
+        };
+
+        let module = self.modules.get(module_name).ok_or_else(no_handler)?;
+        Ok((Arc::clone(module), function))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn budget_checks() {
+        let check = |source: &str| add_budget_checks(source).replace(BUDGET_CHECK, "#");
+
+        // blocks get it on the same line when there's room,
+        assert_eq!(
+            check("fn main() {\n    loop {}\n}"),
+            "fn main() {#()\n    loop {#()}\n}"
+        );
+        assert_eq!(check("fn f() { 1 }"), "fn f() {#()\n 1 }");
+        assert_eq!(
+            check("if a { // hmm\n} else {\n}"),
+            "if a {#() // hmm\n} else {#()\n}"
+        );
+        // but objects, links, strings and comments are left alone.
+        assert_eq!(check("x := {a: 1, \"b\": {}}"), "x := {a: 1, \"b\": {}}");
+        assert_eq!(check("return {}"), "return {}");
+        assert_eq!(
+            check("fn f() -> {} {\n    x := 1\n    {}\n}"),
+            "fn f() -> {} {#()\n    x := 1\n    {}\n}"
+        );
+        assert_eq!(
+            check("f := \\(x) = {}\ng := \\(x) = {\n    x\n}"),
+            "f := \\(x) = {}\ng := \\(x) = {#()\n    x\n}"
+        );
+        assert_eq!(check("link { \"a\" b }"), "link { \"a\" b }");
+        assert_eq!(
+            check("log(\"{ \\\" {\") /* { /* { */ */ // {"),
+            "log(\"{ \\\" {\") /* { /* { */ */ // {"
+        );
+    }
+}
@@ -0,0 +1,157 @@
+use crate::prelude::*;
+use specs::World;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+/// How often the files are checked, there's no need to hit the disk every single frame.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Keeps an eye on some files, by checking when they were last modified every so often.
+pub struct FileWatcher {
+    files: Vec<(PathBuf, Option<SystemTime>)>,
+    last_poll: Instant,
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+#[allow(dead_code)]
+impl FileWatcher {
+    pub fn new() -> Self {
+        Self {
+            files: Vec::new(),
+            last_poll: Instant::now(),
+        }
+    }
+
+    pub fn watch<P: Into<PathBuf>>(&mut self, path: P) {
+        let path = path.into();
+        if !self.files.iter().any(|(watched, _)| *watched == path) {
+            let time = modified(&path);
+            self.files.push((path, time));
+        }
+    }
+
+    /// Forget about any changes to this file so far, i.e. because we just wrote it ourselves.
+    pub fn refresh(&mut self, path: &Path) {
+        if let Some((_, time)) = self.files.iter_mut().find(|(watched, _)| watched == path) {
+            *time = modified(path);
+        }
+    }
+
+    /// The files that have changed since the last time this was called.
+    pub fn changed(&mut self) -> Vec<PathBuf> {
+        if self.last_poll.elapsed() < POLL_INTERVAL {
+            return Vec::new();
+        }
+        self.last_poll = Instant::now();
+
+        self.files
+            .iter_mut()
+            .filter_map(|(path, last_modified)| {
+                let now_modified = modified(path);
+                if now_modified != *last_modified {
+                    *last_modified = now_modified;
+                    Some(path.clone())
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+}
+
+/// Reloads whatever content has changed on disk since the last time this was called,
+/// so artists can see what they're doing without restarting the game.
+/// Anything that goes wrong ends up in the LoadErrors, like it would at startup.
+pub fn hot_reload(world: &mut World, wgpu_state: &mut WgpuState) {
+    let instances_path = world.read_resource::<Assemblager>().instances_path();
+    let changed = {
+        let mut watcher = world.write_resource::<FileWatcher>();
+        // the slot could've changed since the last time
+        watcher.watch(instances_path.clone());
+        watcher.changed()
+    };
+    if changed.is_empty() {
+        return;
+    }
+
+    let mut errors = LoadErrors::default();
+    let mut rebuild_appearances = false;
+
+    for path in changed.iter() {
+        info!("Reloading {}", path.display());
+
+        if path == Path::new(TYPES_PATH) {
+            world
+                .write_resource::<Assemblager>()
+                .reload_types(&mut errors);
+            // the types' appearances could've changed too.
+            rebuild_appearances = true;
+        } else if path == Path::new(INDEXES_PATH) {
+            *world.write_resource::<ImageBundle>() = ImageBundle::new(&mut errors);
+            rebuild_appearances = true;
+        } else if path == Path::new(SPRITESHEET_PATH) {
+            match image::open(path) {
+                Ok(image) => {
+                    let texels = image.to_rgba();
+                    wgpu_state.reload_spritesheet(&texels);
+                    *world.write_resource::<SpritesheetDimensions>() = SpritesheetDimensions {
+                        x: texels.dimensions().0 as f32,
+                        y: texels.dimensions().1 as f32,
+                    };
+                    rebuild_appearances = true;
+                }
+                Err(e) => errors.push(LoadError::new(SPRITESHEET_PATH, "", e)),
+            }
+        } else if path == Path::new(DIALOGUE_PATH) {
+            *world.write_resource::<Dialogues>() = Dialogues::load(&mut errors);
+        } else if path == Path::new(QUESTS_PATH) {
+            *world.write_resource::<Quests>() = Quests::load(&mut errors);
+        } else if path == Path::new(ITEMS_PATH) {
+            *world.write_resource::<ItemDefinitions>() = ItemDefinitions::load(&mut errors);
+        } else if *path == instances_path {
+            reload_instances(world, instances_path.clone(), &mut errors);
+        }
+    }
+
+    // the uvs are all relative to the spritesheet, so they all need to be figured out again.
+    if rebuild_appearances {
+        use specs::Join;
+
+        for appear_builder in (&mut world.write_storage::<AppearanceBuilder>()).join() {
+            appear_builder.built = false;
+        }
+    }
+
+    world.write_resource::<LoadErrors>().0.extend(errors.0);
+}
+
+/// Out with the old entities, in with the new ones.
+/// The play time carries on from the new save's, so it's right the next time it's saved.
+pub fn reload_instances(
+    world: &mut World,
+    instances_path: PathBuf,
+    errors: &mut LoadErrors,
+) -> SaveHeader {
+    use specs::Join;
+
+    {
+        let ents = world.entities();
+        let assemblaged = world.read_storage::<Assemblaged>();
+        for (ent, _) in (&ents, &assemblaged).join() {
+            ents.delete(ent).unwrap();
+        }
+    }
+    world.maintain();
+
+    // none of this makes sense for the new entities, and the save brings its own if it has them.
+    *world.write_resource::<ScriptScheduler>() = ScriptScheduler::default();
+    *world.write_resource::<QuestLog>() = QuestLog::default();
+    *world.write_resource::<DialogueState>() = DialogueState::default();
+
+    let save_header = Assemblager::load_save_from(instances_path, world, errors);
+    world.write_resource::<LocalState>().play_time = save_header.play_time;
+    save_header
+}
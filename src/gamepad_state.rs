@@ -0,0 +1,202 @@
+use crate::prelude::*;
+use gilrs::{Axis, Button, Gilrs};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{self, prelude::*};
+
+/// Sticks that are barely being pushed are treated as if they aren't pushed at all,
+/// since hardly any of them actually rest at exactly zero.
+pub const STICK_DEADZONE: f32 = 0.15;
+
+/// Everything that's going on with the gamepads, for one frame.
+/// If several are plugged in, they're all treated as one big gamepad.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GamepadInput {
+    pub left_stick: (f32, f32),
+    pub right_stick: (f32, f32),
+    pub left_trigger: f32,
+    pub right_trigger: f32,
+    pub buttons_held: HashSet<Button>,
+}
+
+impl GamepadInput {
+    /// The left stick as a movement direction in the game world, with a length from 0 to 1.
+    /// Pushing the stick up moves you up the screen, which is toward negative y.
+    pub fn left_stick_movement(&self) -> glm::Vec3 {
+        let (x, y) = self.left_stick;
+        let movement = glm::vec3(x, -y, 0.0);
+        let magnitude = glm::length(&movement);
+
+        if magnitude < STICK_DEADZONE {
+            glm::zero()
+        } else if magnitude > 1.0 {
+            movement / magnitude
+        } else {
+            movement
+        }
+    }
+}
+
+enum GamepadSource {
+    Gilrs(Gilrs),
+    // frames that are played back one at a time, then nothing's pressed.
+    Virtual(std::vec::IntoIter<GamepadInput>),
+    None,
+}
+
+/// Polls gilrs for gamepads. On Linux this goes through evdev, so a virtual gamepad
+/// made with uinput (i.e. with python-evdev's `UInput`) shows up just like a real one.
+/// Without one of those, `virtual_pad` plays back frames without ever touching gilrs.
+pub struct GamepadState {
+    source: GamepadSource,
+}
+
+impl GamepadState {
+    pub fn new() -> Self {
+        let source = Gilrs::new()
+            .map_err(|e| warn!("Couldn't start gamepad support, so no gamepads: {}", e))
+            .map(GamepadSource::Gilrs)
+            .unwrap_or(GamepadSource::None);
+
+        Self { source }
+    }
+
+    /// A gamepad that presses whatever's in `frames`, one frame per poll.
+    pub fn virtual_pad(frames: Vec<GamepadInput>) -> Self {
+        Self {
+            source: GamepadSource::Virtual(frames.into_iter()),
+        }
+    }
+
+    /// Reads a virtual gamepad's frames from a file with one GamepadInput per line, as JSON.
+    pub fn open_virtual_pad(path: &str) -> io::Result<Self> {
+        let mut contents = String::new();
+        File::open(path)?.read_to_string(&mut contents)?;
+        let frames = contents
+            .lines()
+            .map(serde_json::from_str)
+            .collect::<Result<Vec<GamepadInput>, _>>()?;
+
+        Ok(Self::virtual_pad(frames))
+    }
+
+    /// Fills in the gamepad part of this frame's input.
+    pub fn poll(&mut self, input_frame: &mut GamepadInput) {
+        let gilrs = match &mut self.source {
+            GamepadSource::Gilrs(gilrs) => gilrs,
+            GamepadSource::Virtual(frames) => {
+                *input_frame = frames.next().unwrap_or_default();
+                return;
+            }
+            GamepadSource::None => return,
+        };
+
+        // gilrs only updates its idea of the gamepads' state as events are pulled out of it.
+        while let Some(gilrs::Event { id, event, .. }) = gilrs.next_event() {
+            match event {
+                gilrs::EventType::Connected => info!("Gamepad {} connected", id),
+                gilrs::EventType::Disconnected => info!("Gamepad {} disconnected", id),
+                _ => {}
+            }
+        }
+
+        *input_frame = GamepadInput::default();
+        for (_, gamepad) in gilrs.gamepads() {
+            let add_axis = |total: f32, axis| (total + gamepad.value(axis)).max(-1.0).min(1.0);
+            let trigger = |button| {
+                gamepad
+                    .button_data(button)
+                    .map(|data| data.value())
+                    .unwrap_or(0.0)
+            };
+
+            input_frame.left_stick.0 = add_axis(input_frame.left_stick.0, Axis::LeftStickX);
+            input_frame.left_stick.1 = add_axis(input_frame.left_stick.1, Axis::LeftStickY);
+            input_frame.right_stick.0 = add_axis(input_frame.right_stick.0, Axis::RightStickX);
+            input_frame.right_stick.1 = add_axis(input_frame.right_stick.1, Axis::RightStickY);
+            input_frame.left_trigger = input_frame.left_trigger.max(trigger(Button::LeftTrigger2));
+            input_frame.right_trigger = input_frame
+                .right_trigger
+                .max(trigger(Button::RightTrigger2));
+
+            input_frame.buttons_held.extend(
+                [
+                    Button::South,
+                    Button::East,
+                    Button::North,
+                    Button::West,
+                    Button::LeftTrigger,
+                    Button::LeftTrigger2,
+                    Button::RightTrigger,
+                    Button::RightTrigger2,
+                    Button::Select,
+                    Button::Start,
+                    Button::Mode,
+                    Button::LeftThumb,
+                    Button::RightThumb,
+                    Button::DPadUp,
+                    Button::DPadDown,
+                    Button::DPadLeft,
+                    Button::DPadRight,
+                ]
+                .iter()
+                .filter(|button| gamepad.is_pressed(**button)),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stick(x: f32, y: f32) -> GamepadInput {
+        GamepadInput {
+            left_stick: (x, y),
+            ..GamepadInput::default()
+        }
+    }
+
+    #[test]
+    fn deadzone() {
+        assert_eq!(stick(0.0, 0.0).left_stick_movement(), glm::zero());
+        assert_eq!(stick(0.1, -0.1).left_stick_movement(), glm::zero());
+        assert_ne!(
+            stick(STICK_DEADZONE, 0.0).left_stick_movement(),
+            glm::zero()
+        );
+    }
+
+    #[test]
+    fn stick_magnitude() {
+        // halfway is half speed, and up the stick is up the screen.
+        let movement = stick(0.0, 0.5).left_stick_movement();
+        assert!((movement - glm::vec3(0.0, -0.5, 0.0)).norm() < 1e-6);
+
+        // the corners of square gates are as fast as you can go, no faster.
+        let movement = stick(1.0, 1.0).left_stick_movement();
+        assert!((glm::length(&movement) - 1.0).abs() < 1e-6);
+        assert!((movement.x + movement.y).abs() < 1e-6);
+    }
+
+    #[test]
+    fn virtual_pad() {
+        let mut pressed = GamepadInput::default();
+        pressed.buttons_held.insert(Button::South);
+
+        let mut pad = GamepadState::virtual_pad(vec![stick(0.5, 0.0), pressed]);
+        let mut input = GamepadInput::default();
+
+        pad.poll(&mut input);
+        assert_eq!(input.left_stick, (0.5, 0.0));
+
+        pad.poll(&mut input);
+        assert_eq!(input.left_stick, (0.0, 0.0));
+        assert!(input.buttons_held.contains(&Button::South));
+
+        // once it's out of frames, the pad's just let go of.
+        pad.poll(&mut input);
+        assert!(input.buttons_held.is_empty());
+    }
+}
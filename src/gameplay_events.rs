@@ -0,0 +1,13 @@
+/// Something that happened during a tick that more than one system might want to know about.
+/// Entities are referred to by their persistent ids, so it doesn't matter if they're gone.
+#[derive(Debug, Clone, PartialEq)]
+pub enum GameplayEvent {
+    // two entities started touching
+    Collided(u64, u64),
+    // the player interacted with something, whether that started a dialogue or a script.
+    Interacted { player: u64, with: u64 },
+}
+
+/// Every GameplayEvent from the current tick, it's emptied once the tick is over.
+#[derive(Default, Debug)]
+pub struct GameplayEvents(pub Vec<GameplayEvent>);
@@ -0,0 +1,447 @@
+use crate::prelude::*;
+use specs::World;
+
+/// How many frames `--headless` runs for when it isn't told otherwise.
+pub const DEFAULT_FRAME_COUNT: usize = 600;
+/// There's no window, but the camera math still wants a frame to project onto.
+const FRAME_SIZE: (f64, f64) = (1366.0, 768.0);
+/// Headless runs are for testing, so they're as deterministic as they can be.
+const SEED: u64 = 0;
+
+/// Runs the same systems and scripts as the windowed game, minus the window, the dev ui and the
+/// GPU. Useful for CI, and for poking at quests from tests.
+pub struct Headless {
+    pub world: World,
+    dispatchers: crate::Dispatchers,
+    dyon_state: DyonState,
+}
+
+impl Headless {
+    pub fn new() -> Self {
+        let local_state = LocalState::new(FRAME_SIZE.0, FRAME_SIZE.1);
+        let (world, dispatchers) = crate::build_world(
+            local_state,
+            SpritesheetDimensions::from_spritesheet(),
+            SEED,
+            None,
+        );
+
+        Self {
+            world,
+            dispatchers,
+            dyon_state: DyonState::new(),
+        }
+    }
+
+    /// Feed one frame of input to the world, then step it exactly as `main` would.
+    /// The input's duration is ignored, each frame lasts exactly one tick.
+    pub fn step(&mut self, input: UserInput) {
+        self.feed(UserInput {
+            seconds: TICK_SECONDS,
+            ..input
+        });
+    }
+
+    pub fn run<I: IntoIterator<Item = UserInput>>(&mut self, frames: I) {
+        for frame in frames {
+            self.step(frame);
+        }
+    }
+
+    /// Plays back a recording, frame durations and all, so it goes exactly like it did live.
+    /// It starts from the slot it was recorded in, and whatever's changed on disk since then
+    /// is warned about, since the run could go differently because of it.
+    pub fn replay(&mut self, replay: InputReplay) {
+        let header = replay.header.clone();
+        self.world.add_resource(GameRng::new(header.seed));
+
+        if header.slot.is_some() {
+            self.world.write_resource::<Assemblager>().slot = header.slot.clone();
+            let mut errors = LoadErrors::default();
+            reload_instances(
+                &mut self.world,
+                instances_path_for(header.slot.as_ref().map(String::as_str)),
+                &mut errors,
+            );
+            self.world.write_resource::<LoadErrors>().0.extend(errors.0);
+        }
+        for mismatch in header.mismatches(&ReplayHeader::new(header.seed, header.slot.clone())) {
+            warn!("The replay might not reproduce the recording: {}", mismatch);
+        }
+
+        for frame in replay {
+            self.feed(frame);
+        }
+    }
+
+    fn feed(&mut self, input: UserInput) {
+        self.world
+            .write_resource::<LocalState>()
+            .update_from_input(input);
+
+        crate::simulate(&mut self.world, &mut self.dispatchers, &mut self.dyon_state);
+
+        // there's no dev ui to read the console from, so it goes to the log instead.
+        for message in self.take_console().lines() {
+            info!("[dyon] {}", message);
+        }
+    }
+
+    /// Runs a snippet of Dyon with a `fn main()` in it, right now, between frames.
+    pub fn eval(&mut self, source: &str) -> Result<(), String> {
+        self.dyon_state.eval(&mut self.world, source)
+    }
+
+    /// Empties the Dyon console, returning whatever the scripts had logged to it.
+    pub fn take_console(&mut self) -> String {
+        let mut dyon_console = self.world.write_resource::<DyonConsole>();
+        std::mem::replace(&mut dyon_console.0, String::new())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use specs::Join;
+    use winit::VirtualKeyCode;
+
+    fn position(headless: &Headless, ent: specs::Entity) -> glm::TVec2<f32> {
+        let physes = headless.world.read_storage::<Phys>();
+        headless
+            .world
+            .read_resource::<PhysState>()
+            .location(physes.get(ent).unwrap())
+            .unwrap()
+            .xy()
+    }
+
+    fn player(headless: &Headless) -> specs::Entity {
+        let world = &headless.world;
+        let (_, ent) = (&world.read_storage::<MovementControls>(), &world.entities())
+            .join()
+            .next()
+            .unwrap();
+        ent
+    }
+
+    #[test]
+    fn replays_end_up_where_the_recording_did() {
+        let path =
+            std::env::temp_dir().join(format!("stockrpg-replay-{}.json", std::process::id()));
+        let path = path.to_str().unwrap();
+
+        // wander around in a square, swinging every so often.
+        let frames = [
+            VirtualKeyCode::D,
+            VirtualKeyCode::S,
+            VirtualKeyCode::A,
+            VirtualKeyCode::W,
+        ]
+        .iter()
+        .flat_map(|&key| {
+            (0..30).map(move |frame| {
+                if frame % 10 == 0 {
+                    UserInput::with_keys_held(&[key, VirtualKeyCode::Space])
+                } else {
+                    UserInput::with_keys_held(&[key])
+                }
+            })
+        })
+        .map(|input| UserInput {
+            seconds: TICK_SECONDS,
+            ..input
+        })
+        .collect::<Vec<_>>();
+
+        let recorded = {
+            let mut headless = Headless::new();
+            let mut recorder = InputRecorder::create(path, &ReplayHeader::new(SEED, None)).unwrap();
+            for frame in frames {
+                recorder.record(&frame).unwrap();
+                headless.step(frame);
+            }
+            position(&headless, player(&headless))
+        };
+
+        let replayed = {
+            let mut headless = Headless::new();
+            headless.replay(InputReplay::open(path).unwrap());
+            position(&headless, player(&headless))
+        };
+        std::fs::remove_file(path).unwrap();
+
+        assert_eq!(recorded, replayed);
+    }
+
+    // the closest business chicken to the player, so nothing else is in the way.
+    fn business_chicken(headless: &Headless) -> specs::Entity {
+        let world = &headless.world;
+        let player_pos = position(headless, player(headless));
+        let mut chickens = (&world.read_storage::<ScriptingIds>(), &world.entities())
+            .join()
+            .filter(|(ScriptingIds { ids }, _)| ids.iter().any(|id| id == "business chicken"))
+            .map(|(_, ent)| ent)
+            .collect::<Vec<_>>();
+        chickens.sort_by(|a, b| {
+            let distance = |ent| glm::distance(&position(headless, ent), &player_pos);
+            distance(*a).partial_cmp(&distance(*b)).unwrap()
+        });
+        chickens[0]
+    }
+
+    // walks the player over to `ent` with the same keys a player would use.
+    fn walk_to(headless: &mut Headless, ent: specs::Entity) {
+        let player = player(headless);
+        for _ in 0..(10.0 / TICK_SECONDS) as usize {
+            let offset = position(headless, ent) - position(headless, player);
+            if glm::length(&offset) < 1.5 {
+                break;
+            }
+
+            let mut keys = vec![];
+            if offset.x > 0.2 {
+                keys.push(VirtualKeyCode::D);
+            } else if offset.x < -0.2 {
+                keys.push(VirtualKeyCode::A);
+            }
+            // the camera looks down the y axis backwards, so up is -y.
+            if offset.y > 0.2 {
+                keys.push(VirtualKeyCode::S);
+            } else if offset.y < -0.2 {
+                keys.push(VirtualKeyCode::W);
+            }
+            headless.step(UserInput::with_keys_held(&keys));
+        }
+        for _ in 0..10 {
+            headless.step(UserInput::default());
+        }
+        assert!(glm::distance(&position(headless, ent), &position(headless, player)) < 2.0);
+    }
+
+    fn stick(x: f32, y: f32) -> UserInput {
+        let mut input = UserInput::default();
+        input.gamepad.left_stick = (x, y);
+        input
+    }
+
+    // how far the player gets in a second of holding the stick like that.
+    fn distance_walked(x: f32, y: f32) -> f32 {
+        let mut headless = Headless::new();
+        let start = position(&headless, player(&headless));
+        headless.run((0..(1.0 / TICK_SECONDS) as usize).map(|_| stick(x, y)));
+        glm::distance(&start, &position(&headless, player(&headless)))
+    }
+
+    #[test]
+    fn stick_speed() {
+        // a barely touched stick doesn't move you at all,
+        assert!(distance_walked(0.1, 0.0) < 1e-4);
+
+        // but otherwise you go as fast as it's pushed.
+        let full = distance_walked(1.0, 0.0);
+        let half = distance_walked(0.5, 0.0);
+        assert!(full > 0.5);
+        assert!((half / full - 0.5).abs() < 0.05);
+    }
+
+    #[test]
+    fn pad_interact() {
+        let mut headless = Headless::new();
+        let chicken = business_chicken(&headless);
+        walk_to(&mut headless, chicken);
+
+        let mut input = UserInput::default();
+        input.gamepad.buttons_held.insert(gilrs::Button::South);
+        headless.step(input);
+
+        assert!(headless.world.read_resource::<DialogueState>().is_active());
+    }
+
+    #[test]
+    fn talk_the_business_chicken_into_a_quest() {
+        let mut headless = Headless::new();
+        let chicken = business_chicken(&headless);
+        let chicken_id = id_of_entity(&headless.world, chicken).unwrap();
+        walk_to(&mut headless, chicken);
+
+        // talking starts the dialogue, then it's two lines and the first choice, "I'll get you
+        // your melons.", each a tap of interact.
+        for _ in 0..4 {
+            headless.step(UserInput::with_keys_held(&[VirtualKeyCode::E]));
+            headless.step(UserInput::default());
+        }
+
+        assert!(!headless.world.read_resource::<DialogueState>().is_active());
+        assert_eq!(
+            headless
+                .world
+                .read_resource::<QuestLog>()
+                .status("melon delivery"),
+            QuestStatus::Active
+        );
+        assert_eq!(
+            headless
+                .world
+                .read_resource::<DyonData>()
+                .cache_value(chicken_id, "quests_launched"),
+            Some(serde_json::json!(1.0))
+        );
+    }
+
+    #[test]
+    fn click_through_a_conversation() {
+        let mut headless = Headless::new();
+        let chicken = business_chicken(&headless);
+        walk_to(&mut headless, chicken);
+        headless.step(UserInput::with_keys_held(&[VirtualKeyCode::E]));
+
+        // clicks go through the input like anything else, so they can be replayed too.
+        let click = |dialogue_click| UserInput {
+            dialogue_click: Some(dialogue_click),
+            ..UserInput::default()
+        };
+        headless.run(vec![
+            click(DialogueClick::Advance),
+            click(DialogueClick::Choose(0)),
+            click(DialogueClick::Advance),
+        ]);
+
+        assert!(!headless.world.read_resource::<DialogueState>().is_active());
+        assert_eq!(
+            headless
+                .world
+                .read_resource::<QuestLog>()
+                .status("melon delivery"),
+            QuestStatus::Active
+        );
+    }
+
+    #[test]
+    fn interact_without_a_player() {
+        let mut headless = Headless::new();
+        let player = player(&headless);
+        headless.world.delete_entity(player).unwrap();
+        headless.world.maintain();
+
+        // there's no one to do the interacting, so nothing happens.
+        headless.step(UserInput::with_keys_held(&[VirtualKeyCode::E]));
+        assert!(!headless.world.read_resource::<DialogueState>().is_active());
+    }
+
+    #[test]
+    fn conversations_end_when_the_speaker_is_gone() {
+        let mut headless = Headless::new();
+        let chicken = business_chicken(&headless);
+        walk_to(&mut headless, chicken);
+
+        headless.step(UserInput::with_keys_held(&[VirtualKeyCode::E]));
+        headless.step(UserInput::default());
+        assert!(headless.world.read_resource::<DialogueState>().is_active());
+
+        // mid-conversation, the chicken's gone, and talking to it again shouldn't panic.
+        headless.world.delete_entity(chicken).unwrap();
+        headless.world.maintain();
+        headless.step(UserInput::with_keys_held(&[VirtualKeyCode::E]));
+        assert!(!headless.world.read_resource::<DialogueState>().is_active());
+    }
+
+    #[test]
+    fn events_for_the_same_entity_all_run() {
+        let mut headless = Headless::new();
+        let chicken = business_chicken(&headless);
+        let chicken_id = id_of_entity(&headless.world, chicken).unwrap();
+
+        // two queued up and one put on by hand, all in the same tick.
+        let greeting = || ScriptEvent {
+            function: "business_chicken::business_greeting".to_owned(),
+            payload: ScriptPayload::None,
+        };
+        {
+            let mut events = headless.world.write_resource::<ScriptEvents>();
+            events.push(chicken_id, greeting());
+            events.push(chicken_id, greeting());
+        }
+        headless
+            .world
+            .write_storage::<ScriptEvent>()
+            .insert(chicken, greeting())
+            .unwrap();
+        headless.step(UserInput::default());
+
+        assert_eq!(
+            headless
+                .world
+                .read_resource::<DyonData>()
+                .cache_value(chicken_id, "quests_launched"),
+            Some(serde_json::json!(3.0))
+        );
+    }
+
+    // calls a handler on the player, then steps without emptying the console like `step` does.
+    fn run_handler(headless: &mut Headless, function: &str) {
+        let id = id_of_entity(&headless.world, player(headless)).unwrap();
+        headless.world.write_resource::<ScriptEvents>().push(
+            id,
+            ScriptEvent {
+                function: function.to_owned(),
+                payload: ScriptPayload::None,
+            },
+        );
+
+        headless
+            .world
+            .write_resource::<LocalState>()
+            .update_from_input(UserInput {
+                seconds: TICK_SECONDS,
+                ..UserInput::default()
+            });
+        crate::simulate(
+            &mut headless.world,
+            &mut headless.dispatchers,
+            &mut headless.dyon_state,
+        );
+    }
+
+    #[test]
+    fn scripts_run_with_budget_checks() {
+        let mut headless = Headless::new();
+
+        run_handler(&mut headless, "business_chicken::melons_delivered");
+        assert_eq!(headless.take_console(), "It's over, boys.\n");
+    }
+
+    #[test]
+    fn hanging_handlers_are_stopped() {
+        let mut headless = Headless::new();
+        headless.dyon_state = DyonState::with_scripts("tests/fixtures/scripts");
+
+        for _ in 0..MAX_HANDLER_FAILURES + 1 {
+            run_handler(&mut headless, "hang::hang");
+        }
+
+        // it's stopped every time until it's disabled, then it isn't run at all.
+        let console = headless.take_console();
+        assert_eq!(
+            console.matches("fn hang::hang").count(),
+            MAX_HANDLER_FAILURES as usize + 1
+        );
+        assert!(console.contains("Took longer than"));
+        assert!(console.contains(" --- DISABLED --- "));
+    }
+
+    #[test]
+    fn ambiguous_handlers_are_refused() {
+        let mut headless = Headless::new();
+        headless.dyon_state = DyonState::with_scripts("tests/fixtures/scripts");
+
+        // two modules have a greet, so neither of them is guessed at,
+        run_handler(&mut headless, "greet");
+        let console = headless.take_console();
+        assert!(console.contains("more than one script (greet, greets)"));
+        assert!(!console.contains("hello") && !console.contains("hi\n"));
+
+        // but saying which one works.
+        run_handler(&mut headless, "greets::greet");
+        assert_eq!(headless.take_console(), "hi\n");
+    }
+}
@@ -1,36 +1,53 @@
+use crate::prelude::*;
 use std::collections::HashMap;
 
-const INDEXES_PATH: &str = "./src/data/spritesheet.txt";
+pub const INDEXES_PATH: &str = "./src/data/spritesheet.txt";
 
 pub struct ImageBundle {
     pub map: HashMap<String, [u32; 4]>,
 }
 
 impl ImageBundle {
-    pub fn new() -> Self {
-        use std::fs;
+    /// Lines that can't be parsed are skipped, and reported in `errors`.
+    pub fn new(errors: &mut LoadErrors) -> Self {
+        // each line looks like "name = x y width height"
+        pub fn parse_line(line: &str) -> Result<(String, [u32; 4]), String> {
+            let words: Vec<&str> = line.split_whitespace().collect();
+            if words.len() != 6 || words[1] != "=" {
+                return Err(format!(
+                    "Expected \"name = x y width height\", found {:?}",
+                    line
+                ));
+            }
 
-        pub fn parse(filename: &str) -> HashMap<String, [u32; 4]> {
-            let contents = fs::read_to_string(filename)
-                .expect("Make sure your spritesheet file is named spritesheet.txt");
-            let lines = contents.lines();
-            let mut map: HashMap<String, [u32; 4]> = HashMap::new();
-            for line in lines {
-                let words: Vec<&str> = line.split(" ").collect();
-                let name = words[0];
-                let coords: [u32; 4] = [
-                    words[2].parse::<u32>().unwrap(),
-                    words[3].parse::<u32>().unwrap(),
-                    words[4].parse::<u32>().unwrap(),
-                    words[5].parse::<u32>().unwrap(),
-                ];
-                map.insert(name.to_string(), coords);
+            let mut coords = [0; 4];
+            for (coord, word) in coords.iter_mut().zip(&words[2..]) {
+                *coord = word
+                    .parse::<u32>()
+                    .map_err(|e| format!("Couldn't parse {:?} as a coordinate: {}", word, e))?;
             }
-            map
+            Ok((words[0].to_string(), coords))
         }
 
-        Self {
-            map: parse(INDEXES_PATH),
+        let contents = read_content_file(INDEXES_PATH, errors).unwrap_or_default();
+        let mut map: HashMap<String, [u32; 4]> = HashMap::new();
+        for (index, line) in contents.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            match parse_line(line) {
+                Ok((name, coords)) => {
+                    map.insert(name, coords);
+                }
+                Err(e) => errors.push(LoadError::new(
+                    INDEXES_PATH,
+                    format!("line {}", index + 1),
+                    e,
+                )),
+            }
         }
+
+        Self { map }
     }
 }
@@ -0,0 +1,121 @@
+use crate::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, prelude::*};
+use winit::{MouseButton, VirtualKeyCode};
+
+pub const BINDINGS_PATH: &str = "./src/data/bindings.json";
+/// Every action the game asks about, so missing bindings can be pointed out up front.
+const ACTIONS: [&str; 10] = [
+    "attack",
+    "editor_modifier",
+    "editor_toggle_mouselock",
+    "explode",
+    "interact",
+    "move_down",
+    "move_left",
+    "move_right",
+    "move_up",
+    "save",
+];
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum InputButton {
+    Key(VirtualKeyCode),
+    Mouse(MouseButton),
+    Gamepad(gilrs::Button),
+}
+impl InputButton {
+    pub fn name(&self) -> String {
+        match self {
+            InputButton::Key(key) => format!("{:?}", key),
+            InputButton::Mouse(button) => format!("Mouse {:?}", button),
+            InputButton::Gamepad(button) => format!("Pad {:?}", button),
+        }
+    }
+}
+
+/// Buttons that all have to be held down at once to trigger an action, i.e. LControl + S.
+pub type Chord = Vec<InputButton>;
+
+/// Maps the names of actions, like "interact", onto the chords that trigger them,
+/// so that systems don't need to care which keys are actually being pressed.
+pub struct InputBindings {
+    pub actions: BTreeMap<String, Vec<Chord>>,
+    // the action that the dev ui is waiting on a new chord for, if any.
+    pub rebinding: Option<String>,
+}
+
+#[allow(dead_code)]
+impl InputBindings {
+    /// If the bindings can't be loaded, nothing's bound, and what went wrong is in `errors`.
+    pub fn load(errors: &mut LoadErrors) -> Self {
+        let actions = read_content_file(BINDINGS_PATH, errors)
+            .and_then(|data| {
+                serde_json::from_str(&data)
+                    .map_err(|e| errors.push(LoadError::from_json_error(BINDINGS_PATH, e)))
+                    .ok()
+            })
+            .unwrap_or_default();
+
+        for action in ACTIONS.iter().filter(|a| !actions.contains_key(**a)) {
+            warn!("No bindings found for action: {}", action);
+        }
+
+        Self {
+            actions,
+            rebinding: None,
+        }
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let mut file = File::create(BINDINGS_PATH)?;
+        file.write_all(serde_json::to_string(&self.actions)?.as_bytes())
+    }
+
+    // unbound actions are warned about when the bindings are loaded, they're just never held.
+    fn chords(&self, action: &str) -> impl Iterator<Item = &Chord> {
+        self.actions.get(action).into_iter().flatten()
+    }
+
+    /// Is every button in one of the action's chords being held down?
+    pub fn held(&self, action: &str, ls: &LocalState) -> bool {
+        self.chords(action)
+            .any(|chord| chord.iter().all(|button| ls.is_held(button)))
+    }
+
+    /// Was one of the action's chords completed this frame?
+    pub fn tapped(&self, action: &str, ls: &LocalState) -> bool {
+        self.chords(action).any(|chord| {
+            chord.iter().all(|button| ls.is_held(button))
+                && chord
+                    .iter()
+                    .any(|button| ls.tapped_buttons.contains(button))
+        })
+    }
+
+    /// Was one of the action's chords completed since the last tick?
+    /// Systems that run every tick should use this rather than `tapped`.
+    pub fn tick_tapped(&self, action: &str, ls: &LocalState) -> bool {
+        self.tick_tapped_chord(action, ls).is_some()
+    }
+
+    /// Like `tick_tapped`, but says which of the action's chords it was.
+    pub fn tick_tapped_chord(&self, action: &str, ls: &LocalState) -> Option<&Chord> {
+        self.chords(action).find(|chord| {
+            chord.iter().all(|button| ls.is_held(button))
+                && chord
+                    .iter()
+                    .any(|button| ls.tick_tapped_buttons.contains(button))
+        })
+    }
+
+    pub fn chord_name(chord: &Chord) -> String {
+        chord
+            .iter()
+            .map(InputButton::name)
+            .collect::<Vec<_>>()
+            .join(" + ")
+    }
+}
@@ -0,0 +1,53 @@
+use crate::prelude::*;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+pub const ITEMS_PATH: &str = "./src/data/items.json";
+
+fn default_max_stack() -> u32 {
+    1
+}
+
+/// What every item with the same key has in common.
+#[derive(Deserialize, Debug, Clone)]
+pub struct ItemDefinition {
+    // what players see it called
+    pub name: String,
+    // how many can share one inventory slot
+    #[serde(default = "default_max_stack")]
+    pub max_stack: u32,
+}
+
+/// Every kind of item, keyed by the name Item components and scripts refer to them by.
+#[derive(Default, Debug, Clone)]
+pub struct ItemDefinitions(pub HashMap<String, ItemDefinition>);
+
+impl ItemDefinitions {
+    pub fn load(errors: &mut LoadErrors) -> Self {
+        let mut items: HashMap<String, ItemDefinition> = read_content_file(ITEMS_PATH, errors)
+            .and_then(|data| {
+                serde_json::from_str(&data)
+                    .map_err(|e| errors.push(LoadError::from_json_error(ITEMS_PATH, e)))
+                    .ok()
+            })
+            .unwrap_or_default();
+
+        // a stack of 0 would never fit anything.
+        for (key, item) in items.iter_mut().filter(|(_, item)| item.max_stack == 0) {
+            errors.push(LoadError::new(
+                ITEMS_PATH,
+                key.as_str(),
+                "max_stack can't be 0, using 1 instead.",
+            ));
+            item.max_stack = 1;
+        }
+
+        ItemDefinitions(items)
+    }
+
+    pub fn get(&self, key: &str) -> Result<&ItemDefinition, String> {
+        self.0
+            .get(key)
+            .ok_or_else(|| format!("There's no item called {}.", key))
+    }
+}
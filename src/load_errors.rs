@@ -0,0 +1,80 @@
+use log::error;
+use std::fmt;
+
+/// Something that went wrong while loading content, like a typo in types.json.
+/// These are collected instead of panicking, so the game can start with whatever did load.
+#[derive(Debug, Clone)]
+pub struct LoadError {
+    pub file: String,
+    // i.e. "line 4", or a json path like "entities[3].components[1]"
+    pub location: String,
+    // which component couldn't be loaded, if it's a component's fault.
+    pub component: Option<String>,
+    pub message: String,
+}
+
+impl LoadError {
+    pub fn new<F: Into<String>, L: Into<String>, M: ToString>(
+        file: F,
+        location: L,
+        message: M,
+    ) -> Self {
+        Self {
+            file: file.into(),
+            location: location.into(),
+            component: None,
+            message: message.to_string(),
+        }
+    }
+
+    pub fn with_component<C: Into<String>>(self, component: C) -> Self {
+        Self {
+            component: Some(component.into()),
+            ..self
+        }
+    }
+
+    /// For when a json file can't even be parsed, serde_json knows where it went wrong.
+    pub fn from_json_error<F: Into<String>>(file: F, err: serde_json::Error) -> Self {
+        Self::new(
+            file,
+            format!("line {}, column {}", err.line(), err.column()),
+            err,
+        )
+    }
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.file)?;
+        if !self.location.is_empty() {
+            write!(f, " @ {}", self.location)?;
+        }
+        if let Some(component) = &self.component {
+            write!(f, " ({})", component)?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+/// Every LoadError since the game started (or since they were dismissed in the dev ui).
+#[derive(Default, Debug)]
+pub struct LoadErrors(pub Vec<LoadError>);
+
+impl LoadErrors {
+    pub fn push(&mut self, error: LoadError) {
+        error!("{}", error);
+        self.0.push(error);
+    }
+}
+
+/// Reads a whole content file, reporting it and giving back nothing if that can't be done.
+pub fn read_content_file<P: AsRef<std::path::Path>>(
+    path: P,
+    errors: &mut LoadErrors,
+) -> Option<String> {
+    let path = path.as_ref();
+    std::fs::read_to_string(path)
+        .map_err(|e| errors.push(LoadError::new(path.display().to_string(), "", e)))
+        .ok()
+}
@@ -1,6 +1,11 @@
 use crate::prelude::*;
 use std::collections::HashSet;
-use std::time::Instant;
+
+/// How long a single tick of gameplay and physics lasts, in seconds.
+pub const TICK_SECONDS: f32 = 1.0 / 60.0;
+/// If a frame takes forever, only this much of it is simulated, otherwise
+/// the ticks needed to catch up would just make the next frame take even longer.
+const MAX_FRAME_SECONDS: f32 = 0.25;
 
 #[derive(Debug, Clone)]
 pub struct LocalState {
@@ -8,14 +13,22 @@ pub struct LocalState {
     pub frame_height: f64,
     pub camera: Camera,
     pub perspective_projection: glm::TMat4<f32>,
-    pub last_update: std::time::Instant,
     pub elapsed_time: f32,
+    // like elapsed_time, but carried over from save to save.
+    pub play_time: f32,
     pub last_frame_duration: f32,
+    // time that has passed but hasn't been simulated by a tick yet.
+    pub tick_accumulator: f32,
     pub mouse_pos: (f32, f32),
     pub mouse_down: bool,
     pub quit: bool,
     pub last_input: UserInput,
-    pub tapped_keys: HashSet<winit::VirtualKeyCode>,
+    pub tapped_buttons: HashSet<InputButton>,
+    // buttons tapped since the last tick ran. frames and ticks don't line up, so a tap could
+    // otherwise be seen by several ticks, or by none at all.
+    pub tick_tapped_buttons: HashSet<InputButton>,
+    // like tick_tapped_buttons, but for the dialogue window.
+    pub tick_dialogue_click: Option<DialogueClick>,
     pub focused: bool,
 }
 
@@ -26,19 +39,26 @@ impl LocalState {
             .get_inner_size()
             .map(|logical| logical.into())
             .unwrap_or((0.0, 0.0));
+        Self::new(frame_width, frame_height)
+    }
+
+    pub fn new(frame_width: f64, frame_height: f64) -> Self {
         Self {
             frame_width,
             frame_height,
             elapsed_time: 0.0,
+            play_time: 0.0,
             last_frame_duration: 0.0,
+            tick_accumulator: 0.0,
             quit: false,
-            last_update: std::time::Instant::now(),
             camera: Camera::at_position(glm::vec3(0.0, 0.0, 0.0)),
             perspective_projection: LocalState::get_perspective(frame_width, frame_height),
             mouse_pos: (0.0, 0.0),
             mouse_down: false,
             last_input: UserInput::default(),
-            tapped_keys: std::collections::HashSet::new(),
+            tapped_buttons: std::collections::HashSet::new(),
+            tick_tapped_buttons: std::collections::HashSet::new(),
+            tick_dialogue_click: None,
             focused: true,
         }
     }
@@ -82,26 +102,73 @@ impl LocalState {
         }
         assert!(self.frame_width != 0.0 && self.frame_height != 0.0);
 
-        let now = Instant::now();
-        let duration = now.duration_since(self.last_update);
-        let duration = duration.as_secs() as f32 + duration.subsec_nanos() as f32 * 1e-9;
-        self.last_update = now;
+        // whoever made the input decides how long the frame took,
+        // that way headless runs can step the world at a fixed rate.
+        let duration = input.seconds;
         self.elapsed_time += duration;
+        self.play_time += duration;
         self.last_frame_duration = duration;
+        self.tick_accumulator = (self.tick_accumulator + duration).min(MAX_FRAME_SECONDS);
 
         // figure out which keys were just tapped based on the keys that were pressed last frame and
         // the keys that are pressed now. if a key is pressed now, but it wasn't pressed last frame,
         // well it'd have to be fresh pressed, yeah? and that's the best coffee.
-        self.tapped_keys.clear();
+        self.tapped_buttons.clear();
         for key in &input.keys_held {
             if !self.last_input.keys_held.contains(key) {
-                self.tapped_keys.insert(*key);
+                self.tapped_buttons.insert(InputButton::Key(*key));
+            }
+        }
+        // same goes for the mouse and gamepad buttons.
+        for button in &input.mouse_buttons_held {
+            if !self.last_input.mouse_buttons_held.contains(button) {
+                self.tapped_buttons.insert(InputButton::Mouse(*button));
             }
         }
+        for button in &input.gamepad.buttons_held {
+            if !self.last_input.gamepad.buttons_held.contains(button) {
+                self.tapped_buttons.insert(InputButton::Gamepad(*button));
+            }
+        }
+        self.tick_tapped_buttons
+            .extend(self.tapped_buttons.iter().cloned());
+        if input.dialogue_click.is_some() {
+            self.tick_dialogue_click = input.dialogue_click;
+        }
 
         self.last_input = input;
         // self.camera.update_position(&input.keys_held, 5.0 * duration);
     }
+
+    /// Returns true if there's enough unsimulated time left over for another tick,
+    /// taking that time out of the accumulator if so.
+    pub fn start_tick(&mut self) -> bool {
+        if self.tick_accumulator >= TICK_SECONDS {
+            self.tick_accumulator -= TICK_SECONDS;
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn end_tick(&mut self) {
+        self.tick_tapped_buttons.clear();
+        self.tick_dialogue_click = None;
+    }
+
+    pub fn is_held(&self, button: &InputButton) -> bool {
+        match button {
+            InputButton::Key(key) => self.last_input.keys_held.contains(key),
+            InputButton::Mouse(button) => self.last_input.mouse_buttons_held.contains(button),
+            InputButton::Gamepad(button) => self.last_input.gamepad.buttons_held.contains(button),
+        }
+    }
+
+    /// How far between the last tick and the next one the current frame is, from 0 to 1.
+    pub fn tick_alpha(&self) -> f32 {
+        self.tick_accumulator / TICK_SECONDS
+    }
+
     pub fn update_perspective(&mut self) {
         self.perspective_projection =
             LocalState::get_perspective(self.frame_width, self.frame_height);
@@ -9,10 +9,26 @@ mod camera;
 mod compendium;
 mod comps;
 mod dev_ui;
+mod dialogue;
 mod dyon;
+mod dyon_repl;
+mod dyon_scripts;
+mod file_watcher;
+mod gamepad_state;
+mod gameplay_events;
+mod headless;
 mod image_bundle;
+mod input_bindings;
+mod items;
+mod load_errors;
 mod local_state;
+mod nuklear_state;
 mod phys_state;
+mod quests;
+mod replay;
+mod save_file;
+mod script_events;
+mod script_scheduler;
 mod user_input;
 mod wgpu;
 mod winit_state;
@@ -22,8 +38,8 @@ use crate::prelude::*;
 
 use specs::prelude::*;
 use specs::{
-    DispatcherBuilder, Entities, LazyUpdate, ReadExpect, ReadStorage, System, World, WriteExpect,
-    WriteStorage,
+    Dispatcher, DispatcherBuilder, Entities, LazyUpdate, ReadExpect, ReadStorage, System, World,
+    WriteExpect, WriteStorage,
 };
 
 struct AddHitboxesToPhys;
@@ -31,18 +47,22 @@ impl<'a> System<'a> for AddHitboxesToPhys {
     type SystemData = (
         Entities<'a>,
         WriteExpect<'a, PhysState>,
+        ReadStorage<'a, U64Marker>,
         WriteStorage<'a, Hitbox>,
         WriteStorage<'a, Phys>,
     );
 
-    fn run(&mut self, (ents, mut physics_state, mut hitboxes, mut physes): Self::SystemData) {
+    fn run(
+        &mut self,
+        (ents, mut physics_state, markers, mut hitboxes, mut physes): Self::SystemData,
+    ) {
         for (ent, mut hitbox) in (&*ents, hitboxes.drain()).join() {
             // get a handle to the body for the hitbox
             let phys_comp = physics_state.phys_from_hitbox(&mut hitbox);
 
             // add the entity as some user data so that when we find it via raycasting
             // we can detect it for what it is.
-            physics_state.name_as_ent(&phys_comp, Box::new(ent));
+            physics_state.name_as_ent(&phys_comp, ent, markers.get(ent).map(Marker::id));
 
             // add the new physics body to the world.
             physes.insert(ent, phys_comp).unwrap();
@@ -110,60 +130,489 @@ struct Exploding;
 impl<'a> System<'a> for Exploding {
     type SystemData = (
         ReadExpect<'a, LocalState>,
+        ReadExpect<'a, InputBindings>,
         ReadExpect<'a, Assemblager>,
         ReadExpect<'a, LazyUpdate>,
         ReadExpect<'a, PhysState>,
+        WriteExpect<'a, GameRng>,
         Entities<'a>,
         ReadStorage<'a, Phys>,
         WriteStorage<'a, Explodeable>,
+        WriteStorage<'a, Detonate>,
     );
 
-    fn run(&mut self, (ls, assemblager, lu, ps, ents, physes, mut explodeables): Self::SystemData) {
-        use winit::VirtualKeyCode::B;
-
-        if ls.tapped_keys.contains(&B) {
+    fn run(
+        &mut self,
+        (ls, bindings, assemblager, lu, ps, mut rng, ents, physes, mut explodeables, mut detonates): Self::SystemData,
+    ) {
+        // the explode key sets off everything, otherwise it's only what's been told to go off.
+        let everything = bindings.tick_tapped("explode", &ls);
+        if everything {
             info!("kerboom!");
-            (&explodeables, &ents, &physes)
-                .join()
-                .filter_map(|(explo, ent, phys)| {
-                    let pos = *ps.location(phys).unwrap();
-
-                    for _ in 0..explo.chunks_count {
-                        let which_gib: i32 = OsRng::new().unwrap().gen_range(1, 10);
-                        let gib_ent = assemblager.build_at("melon gib", &lu, &ents, pos);
-
-                        lu.insert(
-                            gib_ent,
-                            AppearanceBuilder {
-                                image_name: format!("melon_gib_{:?}", which_gib),
-                                ..AppearanceBuilder::default()
-                            },
-                        );
+        }
+        (&explodeables, &ents, &physes)
+            .join()
+            .filter(|(_, ent, _)| everything || detonates.get(*ent).is_some())
+            .filter_map(|(explo, ent, phys)| {
+                let pos = *ps.location(phys).unwrap();
+
+                for _ in 0..explo.chunks_count {
+                    let which_gib: i32 = rng.0.gen_range(1, 10);
+                    let gib_ent = assemblager.build_at("melon gib", &lu, &ents, pos);
+
+                    lu.insert(
+                        gib_ent,
+                        AppearanceBuilder {
+                            image_name: format!("melon_gib_{:?}", which_gib),
+                            ..AppearanceBuilder::default()
+                        },
+                    );
+
+                    lu.insert(
+                        gib_ent,
+                        ApplyForce {
+                            vec: ApplyForce::random_2d_vec(&mut rng.0) * explo.force.vec.x,
+                            ..explo.force
+                        },
+                    );
+                }
+
+                if explo.delete_entity {
+                    ents.delete(ent).unwrap();
+                }
+
+                // there's no point in removing the component if it was just removed when the
+                // entity was deleted.
+                (explo.delete_component && !explo.delete_entity).as_some(ent)
+            })
+            // the collect and iter serve to make sure explodeables is dropped, so that it can
+            // then be used to remove the explodeable components we'd like to get rid of.
+            .collect::<Vec<_>>()
+            .iter()
+            .for_each(|ent| {
+                explodeables.remove(*ent);
+            });
+        detonates.clear();
+    }
+}
+
+// how far in front of whoever threw it a projectile shows up, so it doesn't start out inside them.
+const PROJECTILE_SPAWN_DISTANCE: f32 = 0.5;
+struct Attacking;
+impl<'a> System<'a> for Attacking {
+    type SystemData = (
+        Entities<'a>,
+        ReadExpect<'a, LocalState>,
+        ReadExpect<'a, InputBindings>,
+        ReadExpect<'a, DialogueState>,
+        ReadExpect<'a, Assemblager>,
+        ReadExpect<'a, LazyUpdate>,
+        ReadExpect<'a, PhysState>,
+        ReadStorage<'a, Phys>,
+        ReadStorage<'a, MovementControls>,
+        ReadStorage<'a, U64Marker>,
+        ReadStorage<'a, Health>,
+        WriteStorage<'a, Attack>,
+        WriteStorage<'a, Damage>,
+        WriteStorage<'a, ApplyForce>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            ents,
+            local_state,
+            bindings,
+            dialogue_state,
+            assemblager,
+            lu,
+            ps,
+            physes,
+            movement_controls,
+            markers,
+            healths,
+            mut attacks,
+            mut damages,
+            mut forces,
+        ): Self::SystemData,
+    ) {
+        // no swinging at people you're talking to.
+        let player_attacking =
+            bindings.tick_tapped("attack", &local_state) && !dialogue_state.is_active();
+
+        for (ent, attack, phys) in (&ents, &mut attacks, &physes).join() {
+            attack.cooldown_left = (attack.cooldown_left - TICK_SECONDS).max(0.0);
+
+            let (pos, velocity) = match ps.rigid_body(phys) {
+                Some(body) => (
+                    body.position().translation.vector,
+                    body.velocity().linear.xy(),
+                ),
+                None => continue,
+            };
+            // they attack whichever way they're going, or were going last.
+            if glm::length(&velocity) > 0.1 {
+                let facing = velocity.normalize();
+                attack.facing = [facing.x, facing.y];
+            }
+
+            if player_attacking && movement_controls.get(ent).is_some() {
+                attack.triggered = true;
+            }
+            let triggered = std::mem::replace(&mut attack.triggered, false);
+            if !triggered || attack.cooldown_left > 0.0 {
+                continue;
+            }
+            attack.cooldown_left = attack.cooldown;
 
-                        lu.insert(
-                            gib_ent,
+            let facing = glm::make_vec2(&attack.facing);
+            let attacker_id = markers.get(ent).map(|marker| marker.id());
+
+            match attack.projectile.clone() {
+                Some(projectile) => {
+                    if !assemblager.assemblages.contains_key(&projectile) {
+                        warn!("There's no assemblage called {} to throw.", projectile);
+                        continue;
+                    }
+
+                    let spawn_pos =
+                        pos + glm::vec3(facing.x, facing.y, 0.0) * PROJECTILE_SPAWN_DISTANCE;
+                    let thrown = assemblager.build_at(&projectile, &lu, &ents, spawn_pos);
+
+                    // it's only got the Projectile from types.json once it's been built.
+                    let direction = attack.facing;
+                    lu.exec(move |world| {
+                        if let Some(projectile) =
+                            world.write_storage::<Projectile>().get_mut(thrown)
+                        {
+                            projectile.owner = attacker_id;
+                            projectile.direction = direction;
+                        }
+                    });
+                }
+                None => {
+                    let hits =
+                        ps.entities_in_arc(&pos, &facing, attack.range, attack.arc.to_radians());
+
+                    for (hit_ent, hit_pos) in hits.into_iter().filter(|(hit, _)| *hit != ent) {
+                        if healths.get(hit_ent).is_some() {
+                            Damage::deal(
+                                &mut damages,
+                                hit_ent,
+                                DamageHit {
+                                    amount: attack.damage,
+                                    kind: attack.kind.clone(),
+                                    source: attacker_id,
+                                },
+                            );
+                        }
+
+                        // straight away from the attacker, unless they're right on top of them.
+                        let away = (hit_pos - pos).xy();
+                        let away = if glm::length(&away) > 0.0 {
+                            away.normalize()
+                        } else {
+                            facing
+                        };
+                        if attack.knockback != 0.0 {
+                            forces
+                                .insert(
+                                    hit_ent,
+                                    ApplyForce {
+                                        vec: glm::vec3(away.x, away.y, 0.0) * attack.knockback,
+                                        ..ApplyForce::default()
+                                    },
+                                )
+                                .unwrap();
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+struct Projectiles;
+impl<'a> System<'a> for Projectiles {
+    type SystemData = (
+        Entities<'a>,
+        ReadExpect<'a, GameplayEvents>,
+        ReadExpect<'a, U64MarkerAllocator>,
+        WriteExpect<'a, PhysState>,
+        ReadStorage<'a, Phys>,
+        ReadStorage<'a, Health>,
+        WriteStorage<'a, Projectile>,
+        WriteStorage<'a, Damage>,
+        WriteStorage<'a, ApplyForce>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            ents,
+            gameplay_events,
+            marker_allocator,
+            mut ps,
+            physes,
+            healths,
+            mut projectiles,
+            mut damages,
+            mut forces,
+        ): Self::SystemData,
+    ) {
+        use nphysics3d::{math::Velocity, object::Body};
+
+        let entity = |id: u64| {
+            marker_allocator
+                .retrieve_entity_internal(id)
+                .filter(|ent| ents.is_alive(*ent))
+        };
+        // projectiles that have hit something or flown too long, and are on their way out.
+        let mut spent = std::collections::HashSet::new();
+
+        for event in gameplay_events.0.iter() {
+            let (one, two) = match event {
+                GameplayEvent::Collided(one, two) => (*one, *two),
+                _ => continue,
+            };
+
+            for &(projectile_id, other_id) in [(one, two), (two, one)].iter() {
+                let (projectile_ent, other_ent) = match (entity(projectile_id), entity(other_id)) {
+                    (Some(projectile_ent), Some(other_ent)) => (projectile_ent, other_ent),
+                    _ => continue,
+                };
+                let projectile = match projectiles.get(projectile_ent) {
+                    Some(projectile) if !spent.contains(&projectile_ent) => projectile,
+                    _ => continue,
+                };
+                // you can't hit yourself with your own axe.
+                if projectile.owner == Some(other_id) {
+                    continue;
+                }
+
+                if healths.get(other_ent).is_some() {
+                    Damage::deal(
+                        &mut damages,
+                        other_ent,
+                        DamageHit {
+                            amount: projectile.damage,
+                            kind: projectile.kind.clone(),
+                            source: projectile.owner,
+                        },
+                    );
+                }
+                if projectile.knockback != 0.0 {
+                    let direction = glm::make_vec2(&projectile.direction);
+                    forces
+                        .insert(
+                            other_ent,
                             ApplyForce {
-                                vec: ApplyForce::random_2d_vec() * explo.force.vec.x,
-                                ..explo.force
+                                vec: glm::vec3(direction.x, direction.y, 0.0)
+                                    * projectile.knockback,
+                                ..ApplyForce::default()
                             },
+                        )
+                        .unwrap();
+                }
+                spent.insert(projectile_ent);
+            }
+        }
+
+        for (ent, projectile, phys) in (&ents, &mut projectiles, &physes).join() {
+            projectile.age += TICK_SECONDS;
+            if projectile.age >= projectile.lifetime {
+                spent.insert(ent);
+            }
+            if spent.contains(&ent) {
+                continue;
+            }
+
+            // friction would slow it down otherwise.
+            let direction = glm::make_vec2(&projectile.direction);
+            let velocity = if glm::length(&direction) > 0.0 {
+                direction.normalize() * projectile.speed
+            } else {
+                direction
+            };
+            if let Some(body) = ps.rigid_body_mut(phys) {
+                let angular = body.velocity().angular;
+                body.set_velocity(Velocity::new(
+                    glm::vec3(velocity.x, velocity.y, 0.0),
+                    angular,
+                ));
+                body.activate();
+            }
+        }
+
+        for ent in spent {
+            ents.delete(ent).unwrap();
+        }
+    }
+}
+
+struct ApplyDamage;
+impl<'a> System<'a> for ApplyDamage {
+    type SystemData = (
+        Entities<'a>,
+        WriteStorage<'a, Damage>,
+        WriteStorage<'a, Health>,
+        ReadStorage<'a, Resistances>,
+        WriteStorage<'a, Invulnerability>,
+        WriteStorage<'a, Regeneration>,
+        WriteStorage<'a, OnDeath>,
+    );
+
+    fn run(
+        &mut self,
+        (ents, mut damages, mut healths, resistances, mut invulns, mut regens, mut on_deaths): Self::SystemData,
+    ) {
+        for invuln in (&mut invulns).join() {
+            invuln.remaining = (invuln.remaining - TICK_SECONDS).max(0.0);
+        }
+
+        for (ent, damage) in (&ents, damages.drain()).join() {
+            let health = match healths.get_mut(ent) {
+                Some(health) => health,
+                None => continue,
+            };
+
+            for hit in damage.hits {
+                // healing isn't resisted, and you can always be healed.
+                if hit.amount < 0.0 {
+                    health.value = (health.value - hit.amount).min(health.max);
+                    continue;
+                }
+
+                if invulns.get(ent).map_or(false, |i| i.remaining > 0.0) {
+                    continue;
+                }
+                let amount = hit.amount
+                    * resistances
+                        .get(ent)
+                        .map_or(1.0, |r| r.multiplier(&hit.kind));
+                if amount <= 0.0 {
+                    continue;
+                }
+
+                health.value = (health.value - amount).max(0.0);
+
+                if let Some(invuln) = invulns.get_mut(ent) {
+                    invuln.remaining = invuln.seconds;
+                }
+                if let Some(regen) = regens.get_mut(ent) {
+                    regen.since_hurt = 0.0;
+                }
+                // whoever got the last hit in gets the credit.
+                if let Some(on_death) = on_deaths.get_mut(ent) {
+                    if health.value <= 0.0 && on_death.state == DeathState::Alive {
+                        on_death.killer = hit.source;
+                    }
+                }
+            }
+        }
+    }
+}
+
+struct Regenerate;
+impl<'a> System<'a> for Regenerate {
+    type SystemData = (WriteStorage<'a, Regeneration>, WriteStorage<'a, Health>);
+
+    fn run(&mut self, (mut regens, mut healths): Self::SystemData) {
+        for (regen, health) in (&mut regens, &mut healths).join() {
+            regen.since_hurt += TICK_SECONDS;
+
+            // the dead stay dead.
+            if health.value > 0.0 && regen.since_hurt >= regen.delay {
+                health.value = (health.value + regen.per_second * TICK_SECONDS).min(health.max);
+            }
+        }
+    }
+}
+
+struct Dying;
+impl<'a> System<'a> for Dying {
+    type SystemData = (
+        Entities<'a>,
+        ReadExpect<'a, Assemblager>,
+        ReadExpect<'a, LazyUpdate>,
+        ReadExpect<'a, PhysState>,
+        ReadStorage<'a, Health>,
+        ReadStorage<'a, Phys>,
+        ReadStorage<'a, Explodeable>,
+        ReadStorage<'a, U64Marker>,
+        WriteStorage<'a, OnDeath>,
+        WriteStorage<'a, Detonate>,
+        WriteExpect<'a, ScriptEvents>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            ents,
+            assemblager,
+            lu,
+            ps,
+            healths,
+            physes,
+            explodeables,
+            markers,
+            mut on_deaths,
+            mut detonates,
+            mut script_events,
+        ): Self::SystemData,
+    ) {
+        for (ent, health, on_death) in (&ents, &healths, &mut on_deaths).join() {
+            match on_death.state {
+                DeathState::Alive if health.value <= 0.0 => {
+                    on_death.state = DeathState::Dying;
+
+                    if let Some(marker) = markers.get(ent) {
+                        let killer = on_death
+                            .killer
+                            .map_or(ScriptPayload::None, ScriptPayload::Entity);
+                        script_events.push(
+                            marker.id(),
+                            on_death.script.clone_with_payload(vec![("killer", killer)]),
                         );
                     }
+                }
+                // the script had a tick to heal them, and it did.
+                DeathState::Dying if health.value > 0.0 => {
+                    on_death.state = DeathState::Alive;
+                    on_death.killer = None;
+                }
+                DeathState::Dying => {
+                    on_death.state = DeathState::Dead;
 
-                    if explo.delete_entity {
-                        ents.delete(ent).unwrap();
+                    let explodeable = explodeables.get(ent).filter(|_| on_death.explode);
+                    if explodeable.is_some() {
+                        detonates.insert(ent, Detonate).unwrap();
                     }
 
-                    // there's no point in removing the component if it was just removed when the
-                    // entity was deleted.
-                    (explo.delete_component && !explo.delete_entity).as_some(ent)
-                })
-                // the collect and iter serve to make sure explodeables is dropped, so that it can
-                // then be used to remove the explodeable components we'd like to get rid of.
-                .collect::<Vec<_>>()
-                .iter()
-                .for_each(|ent| {
-                    explodeables.remove(*ent);
-                });
+                    if let Some(spawn) = &on_death.spawn {
+                        match physes.get(ent).and_then(|phys| ps.location(phys)) {
+                            Some(pos) if assemblager.assemblages.contains_key(spawn) => {
+                                assemblager.build_at(spawn, &lu, &ents, *pos);
+                            }
+                            Some(_) => warn!("There's no assemblage called {} to spawn.", spawn),
+                            None => {
+                                warn!("Can't spawn {} where something without a Phys died.", spawn)
+                            }
+                        }
+                    }
+
+                    // exploding might be about to delete them anyway.
+                    if on_death.delete_entity && !explodeable.map_or(false, |e| e.delete_entity) {
+                        ents.delete(ent).unwrap();
+                    }
+                }
+                // they were brought back somehow.
+                DeathState::Dead if health.value > 0.0 => {
+                    on_death.state = DeathState::Alive;
+                    on_death.killer = None;
+                }
+                _ => {}
+            }
         }
     }
 }
@@ -172,13 +621,12 @@ struct ApplyForces;
 impl<'a> System<'a> for ApplyForces {
     type SystemData = (
         WriteExpect<'a, PhysState>,
-        ReadExpect<'a, LocalState>,
         Entities<'a>,
         WriteStorage<'a, ApplyForce>,
         ReadStorage<'a, Phys>,
     );
 
-    fn run(&mut self, (mut ps, ls, ents, mut forces, physes): Self::SystemData) {
+    fn run(&mut self, (mut ps, ents, mut forces, physes): Self::SystemData) {
         use nphysics3d::{
             math::{Force, ForceType},
             object::Body,
@@ -188,7 +636,7 @@ impl<'a> System<'a> for ApplyForces {
         (&mut forces, &physes, &ents)
             .join()
             .filter_map(|(force, phys, ent)| {
-                force.time_elapsed += ls.last_frame_duration;
+                force.time_elapsed += TICK_SECONDS;
 
                 if force.time_elapsed >= force.duration {
                     Some(ent)
@@ -220,26 +668,78 @@ impl<'a> System<'a> for Interact {
     type SystemData = (
         Entities<'a>,
         ReadExpect<'a, LocalState>,
+        ReadExpect<'a, InputBindings>,
         ReadExpect<'a, PhysState>,
         ReadStorage<'a, Phys>,
         ReadStorage<'a, Interactable>,
         ReadStorage<'a, MovementControls>,
-        WriteStorage<'a, ScriptEvent>,
+        ReadStorage<'a, U64Marker>,
+        WriteExpect<'a, ScriptEvents>,
+        ReadExpect<'a, Dialogues>,
+        ReadExpect<'a, DyonData>,
+        ReadExpect<'a, QuestLog>,
+        WriteExpect<'a, DialogueState>,
+        WriteExpect<'a, GameplayEvents>,
     );
 
     fn run(
         &mut self,
-        (ents, local_state, ps, physes, interactables, movement_controls, mut script_events): Self::SystemData,
+        (
+            ents,
+            local_state,
+            bindings,
+            ps,
+            physes,
+            interactables,
+            movement_controls,
+            markers,
+            mut script_events,
+            dialogues,
+            dyon_data,
+            quest_log,
+            mut dialogue_state,
+            mut gameplay_events,
+        ): Self::SystemData,
     ) {
-        use winit::VirtualKeyCode::E;
+        let facts = DialogueFacts {
+            dyon_data: &dyon_data,
+            quest_log: &quest_log,
+        };
+
+        // while they're talking to someone, the buttons steer the conversation instead,
+        // and so does clicking in the dialogue window.
+        dialogue_state.end_if_gone(&ents, &dialogues);
+        if dialogue_state.is_active() {
+            if bindings.tick_tapped("move_up", &local_state) {
+                dialogue_state.select(-1, &dialogues, &facts);
+            }
+            if bindings.tick_tapped("move_down", &local_state) {
+                dialogue_state.select(1, &dialogues, &facts);
+            }
+
+            let event = match local_state.tick_dialogue_click {
+                Some(DialogueClick::Choose(index)) => dialogue_state.choose(index, &dialogues),
+                Some(DialogueClick::Advance) => dialogue_state.advance(&dialogues, &facts),
+                None if bindings.tick_tapped("interact", &local_state) => {
+                    dialogue_state.advance(&dialogues, &facts)
+                }
+                None => None,
+            };
+            if let Some((id, event)) = event {
+                script_events.push(id, event);
+            }
+            return;
+        }
 
         // minimum distance the interactable must be at to be interacted with
-        if local_state.tapped_keys.contains(&E) {
+        if let Some(chord) = bindings.tick_tapped_chord("interact", &local_state) {
             // grab the player's x and y coordinates from the physics state
-            let (player_pos, player_ent) = {
-                let (phys, _, ent) = (&physes, &movement_controls, &ents).join().next().unwrap();
-                (ps.location(phys).unwrap().xy(), ent.id().clone())
-            };
+            // the player could've died and been deleted.
+            let (player_pos, player_ent) =
+                match (&physes, &movement_controls, &markers).join().next() {
+                    Some((phys, _, marker)) => (ps.location(phys).unwrap().xy(), marker.id()),
+                    None => return,
+                };
 
             let closest_interactable: Option<(&Interactable, specs::Entity)> =
                 (&physes, &interactables, &ents)
@@ -271,56 +771,469 @@ impl<'a> System<'a> for Interact {
                     // turn it from a (distance, (interactable, ent)) into (interactable, ent)
                     .1;
 
-            // if an interactable was close enough, log it's message and launch the scripting event
-            if let Some((Interactable { script }, ent)) = closest_interactable {
-                script_events
-                    .insert(ent, script.clone_with_payload(player_ent))
-                    .unwrap();
+            // if an interactable was close enough, start talking to them or launch the scripting event
+            let closest_interactable = closest_interactable
+                .and_then(|(interactable, ent)| Some((interactable, ent, markers.get(ent)?.id())));
+            if let Some((Interactable { script, dialogue }, ent, id)) = closest_interactable {
+                gameplay_events.0.push(GameplayEvent::Interacted {
+                    player: player_ent,
+                    with: id,
+                });
+
+                let started_dialogue = match dialogue {
+                    Some(tree) => {
+                        dialogue_state.start(tree, (ent, id), player_ent, &dialogues, &facts)
+                    }
+                    None => false,
+                };
+                if started_dialogue {
+                    return;
+                }
+
+                let payload = vec![
+                    ("player", ScriptPayload::Entity(player_ent)),
+                    // which buttons they pressed to do it
+                    (
+                        "input",
+                        ScriptPayload::Text(InputBindings::chord_name(chord)),
+                    ),
+                ];
+                script_events.push(id, script.clone_with_payload(payload));
             }
         }
     }
 }
 
+// the timers scripts have set up turn into ScriptEvents when they go off.
+struct ScriptTimers;
+impl<'a> System<'a> for ScriptTimers {
+    type SystemData = (
+        Entities<'a>,
+        ReadExpect<'a, U64MarkerAllocator>,
+        WriteExpect<'a, ScriptScheduler>,
+        WriteExpect<'a, ScriptEvents>,
+    );
+
+    fn run(
+        &mut self,
+        (ents, marker_allocator, mut scheduler, mut script_events): Self::SystemData,
+    ) {
+        for (handle, timer) in scheduler.advance(TICK_SECONDS) {
+            let alive = marker_allocator
+                .retrieve_entity_internal(timer.entity)
+                .map_or(false, |ent| ents.is_alive(ent));
+
+            if alive {
+                script_events.push(
+                    timer.entity,
+                    ScriptEvent {
+                        function: timer.function,
+                        payload: ScriptPayload::Number(handle as f64),
+                    },
+                );
+            } else {
+                // there's no point in waiting on an entity that's gone.
+                scheduler.cancel(handle);
+            }
+        }
+    }
+}
+
+// quests keep an eye on what's happened this tick, and pay out when they're done.
+struct QuestTracking;
+impl<'a> System<'a> for QuestTracking {
+    type SystemData = (
+        Entities<'a>,
+        ReadExpect<'a, Quests>,
+        ReadExpect<'a, GameplayEvents>,
+        ReadExpect<'a, U64MarkerAllocator>,
+        WriteExpect<'a, QuestLog>,
+        ReadStorage<'a, ScriptingIds>,
+        ReadStorage<'a, MovementControls>,
+        ReadStorage<'a, U64Marker>,
+        ReadStorage<'a, ScriptEvent>,
+        WriteExpect<'a, ScriptEvents>,
+        WriteStorage<'a, Health>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            ents,
+            quests,
+            gameplay_events,
+            marker_allocator,
+            mut quest_log,
+            scripting_ids,
+            movement_controls,
+            markers,
+            event_components,
+            mut script_events,
+            mut healths,
+        ): Self::SystemData,
+    ) {
+        let player = (&movement_controls, &markers, &ents).join().next();
+
+        // what objectives need to know about an entity, by its persistent id.
+        let subject = |id: u64| QuestSubject {
+            id,
+            scripting_ids: marker_allocator
+                .retrieve_entity_internal(id)
+                .and_then(|ent| scripting_ids.get(ent))
+                .map_or(&[][..], |ScriptingIds { ids }| ids.as_slice()),
+            is_player: player.map_or(false, |(_, marker, _)| marker.id() == id),
+        };
+
+        let mut signals = gameplay_events
+            .0
+            .iter()
+            .map(|event| match event {
+                GameplayEvent::Collided(one, two) => {
+                    QuestSignal::Touched(subject(*one), subject(*two))
+                }
+                GameplayEvent::Interacted { with, .. } => QuestSignal::TalkedTo(subject(*with)),
+            })
+            .collect::<Vec<_>>();
+        // the handlers that are about to be called
+        signals.extend(
+            script_events
+                .0
+                .iter()
+                .map(|(_, event)| event)
+                .chain((&event_components).join())
+                .map(|ScriptEvent { function, .. }| QuestSignal::Handled(function.as_str())),
+        );
+
+        let index = scripting_id_index(&scripting_ids, &markers);
+        quest_log.update(&quests, &signals, &index);
+
+        // rewards need someone to go to.
+        let (player_id, player_ent) = match player {
+            Some((_, marker, ent)) => (marker.id(), ent),
+            None => return,
+        };
+        for (quest, reward) in quest_log.take_rewards() {
+            match reward {
+                QuestReward::Script { function } => {
+                    let payload = vec![("quest".to_owned(), ScriptPayload::Text(quest))];
+                    script_events.push(
+                        player_id,
+                        ScriptEvent {
+                            function,
+                            payload: ScriptPayload::Object(payload.into_iter().collect()),
+                        },
+                    );
+                }
+                QuestReward::Heal(amount) => {
+                    if let Some(health) = healths.get_mut(player_ent) {
+                        health.value = health.max.min(health.value + amount);
+                    }
+                }
+                QuestReward::MaxHealth(amount) => {
+                    if let Some(health) = healths.get_mut(player_ent) {
+                        health.max += amount;
+                        health.value += amount;
+                    }
+                }
+            }
+        }
+    }
+}
+
+// items go into the inventory of whoever bumps into them, or interacts with them.
+struct ItemPickup;
+impl<'a> System<'a> for ItemPickup {
+    type SystemData = (
+        Entities<'a>,
+        ReadExpect<'a, GameplayEvents>,
+        ReadExpect<'a, ItemDefinitions>,
+        ReadExpect<'a, U64MarkerAllocator>,
+        WriteStorage<'a, Item>,
+        WriteStorage<'a, Inventory>,
+    );
+
+    fn run(
+        &mut self,
+        (ents, gameplay_events, definitions, marker_allocator, mut items, mut inventories): Self::SystemData,
+    ) {
+        let entity = |id: u64| {
+            marker_allocator
+                .retrieve_entity_internal(id)
+                .filter(|ent| ents.is_alive(*ent))
+        };
+
+        // (the item, whoever might be picking it up, and how they'd be doing it)
+        let pickups = gameplay_events.0.iter().flat_map(|event| match event {
+            GameplayEvent::Collided(one, two) => {
+                vec![(*one, *two, Pickup::Touch), (*two, *one, Pickup::Touch)]
+            }
+            GameplayEvent::Interacted { player, with } => vec![(*with, *player, Pickup::Interact)],
+        });
+
+        for (item_id, picker_id, how) in pickups {
+            let (item_ent, picker_ent) = match (entity(item_id), entity(picker_id)) {
+                (Some(item_ent), Some(picker_ent)) => (item_ent, picker_ent),
+                _ => continue,
+            };
+            let (item, inventory) = match (items.get_mut(item_ent), inventories.get_mut(picker_ent))
+            {
+                // it could've already been picked up by something else this tick
+                (Some(item), Some(inventory)) if item.pickup == how && item.quantity > 0 => {
+                    (item, inventory)
+                }
+                _ => continue,
+            };
+            let definition = match definitions.get(&item.key) {
+                Ok(definition) => definition,
+                Err(e) => {
+                    warn!("Couldn't pick up entity #{}: {}", item_id, e);
+                    continue;
+                }
+            };
+
+            // whatever doesn't fit is left behind
+            item.quantity = inventory.give(&item.key, item.quantity, definition);
+            if item.quantity == 0 {
+                ents.delete(item_ent).unwrap();
+            }
+        }
+    }
+}
+
+// scripts only throw out the caches of the entities they delete themselves,
+// so whatever else deletes an entity would leave its cache lying around forever.
+struct PruneDyonCaches;
+impl<'a> System<'a> for PruneDyonCaches {
+    type SystemData = (
+        Entities<'a>,
+        ReadStorage<'a, U64Marker>,
+        WriteExpect<'a, DyonData>,
+    );
+
+    fn run(&mut self, (ents, markers, mut dyon_data): Self::SystemData) {
+        let alive = (&ents, &markers)
+            .join()
+            .map(|(_, marker)| marker.id())
+            .collect::<std::collections::HashSet<_>>();
+        dyon_data.retain_caches(|id| alive.contains(&id));
+    }
+}
+
 struct KeyboardMovementControls;
 impl<'a> System<'a> for KeyboardMovementControls {
     type SystemData = (
         ReadStorage<'a, MovementControls>, // so you know who's moving
         ReadStorage<'a, Phys>, // because they'll need a physical representation to move.
         ReadExpect<'a, LocalState>, // because you'll need somewhere to pull the movement info from.
+        ReadExpect<'a, InputBindings>, // which tells you what that movement info means.
         WriteExpect<'a, PhysState>, // because you're moving their position in the physical world
+        ReadExpect<'a, DialogueState>, // because you stand still while talking
     );
 
-    fn run(&mut self, (movs, physes, local_state, mut ps): Self::SystemData) {
-        use nphysics3d::{
-            math::{Force, ForceType},
-            object::Body,
-        };
-
-        use winit::VirtualKeyCode;
-        let keys = &local_state.last_input.keys_held;
-        if !keys.contains(&VirtualKeyCode::LControl) {
+    fn run(
+        &mut self,
+        (movs, physes, local_state, bindings, mut ps, dialogue_state): Self::SystemData,
+    ) {
+        // the modifier is for editor shortcuts, so you don't go running off while saving.
+        // you also can't walk away from a conversation.
+        if !bindings.held("editor_modifier", &local_state) && !dialogue_state.is_active() {
             let vertical = glm::vec3(0.0, 1.0, 0.0);
             let horizontal = glm::vec3(-1.0, 0.0, 0.0);
-            let move_vector = keys
-                .iter()
-                .fold(glm::make_vec3(&[0.0, 0.0, 0.0]), |vec, key| match *key {
-                    VirtualKeyCode::A => vec + horizontal,
-                    VirtualKeyCode::D => vec - horizontal,
-                    VirtualKeyCode::S => vec + vertical,
-                    VirtualKeyCode::W => vec - vertical,
-                    _ => vec,
-                });
+            let move_vector = [
+                ("move_left", horizontal),
+                ("move_right", -horizontal),
+                ("move_down", vertical),
+                ("move_up", -vertical),
+            ]
+            .iter()
+            .filter(|(action, _)| bindings.held(action, &local_state))
+            .fold(glm::make_vec3(&[0.0, 0.0, 0.0]), |vec, (_, dir)| vec + dir);
+
+            // buttons always move you at full speed, but if none are held,
+            // the stick moves you as fast as it's pushed.
+            let move_vector = if move_vector != glm::zero() {
+                move_vector.normalize()
+            } else {
+                local_state.last_input.gamepad.left_stick_movement()
+            };
+
             if move_vector != glm::zero() {
                 for (phys, mov) in (&physes, &movs).join() {
-                    let body = ps.rigid_body_mut(phys).unwrap();
+                    steer_body(ps.rigid_body_mut(phys).unwrap(), &move_vector, mov.speed);
+                }
+            }
+        }
+    }
+}
 
-                    body.apply_force(
-                        0,
-                        &Force::linear(
-                            move_vector.normalize() * mov.speed - body.velocity().linear,
-                        ),
-                        ForceType::Force,
-                        true,
+// how close an NPC has to get to somewhere to count as being there.
+const ARRIVAL_DISTANCE: f32 = 0.3;
+// how long an NPC tries to reach a spot it's wandering to before picking another one.
+const WANDER_PATIENCE: f32 = 4.0;
+struct NpcAi;
+impl<'a> System<'a> for NpcAi {
+    type SystemData = (
+        Entities<'a>,
+        WriteExpect<'a, PhysState>,
+        WriteExpect<'a, GameRng>,
+        ReadExpect<'a, DialogueState>,
+        ReadStorage<'a, Phys>,
+        ReadStorage<'a, Health>,
+        ReadStorage<'a, MovementControls>,
+        ReadStorage<'a, ScriptingIds>,
+        ReadStorage<'a, U64Marker>,
+        WriteStorage<'a, AiBehaviour>,
+        WriteExpect<'a, ScriptEvents>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            ents,
+            mut ps,
+            mut rng,
+            dialogue_state,
+            physes,
+            healths,
+            movement_controls,
+            scripting_ids,
+            markers,
+            mut ais,
+            mut script_events,
+        ): Self::SystemData,
+    ) {
+        let player = (&ents, &movement_controls)
+            .join()
+            .next()
+            .map(|(ent, _)| ent);
+        // whoever the player's talking to stands still and listens.
+        let listener = dialogue_state.active.as_ref().map(|active| active.speaker);
+
+        for (ent, ai, phys) in (&ents, &mut ais, &physes).join() {
+            let pos = match ps.location(phys) {
+                Some(pos) => pos.xy(),
+                None => continue,
+            };
+            let health = healths
+                .get(ent)
+                .map(|health| health.value / health.max.max(std::f32::EPSILON));
+            // the dead don't think.
+            if health.map_or(false, |health| health <= 0.0) {
+                continue;
+            }
+            // the on_enter handlers need someone to be called on.
+            let mut on_enter = |event| {
+                if let Some(marker) = markers.get(ent) {
+                    script_events.push(marker.id(), event);
+                }
+            };
+
+            if ai.current.is_none() {
+                let initial = ai.initial.clone();
+                match ai.enter(&initial) {
+                    Ok(Some(event)) => on_enter(event),
+                    Ok(None) => {}
+                    Err(e) => {
+                        warn!("{}", e);
+                        // so that it isn't complained about every tick.
+                        ai.current = Some(initial);
+                    }
+                }
+            }
+            ai.time_in_state += TICK_SECONDS;
+
+            let home = {
+                let location = ps.location(phys).unwrap();
+                let home = ai.home.get_or_insert([location.x, location.y, location.z]);
+                glm::vec2(home[0], home[1])
+            };
+            let target = match &ai.target {
+                AiTarget::Player => player,
+                AiTarget::ScriptingId(id) => (&ents, &scripting_ids)
+                    .join()
+                    .find(|(_, ScriptingIds { ids })| ids.contains(id))
+                    .map(|(ent, _)| ent),
+            }
+            .filter(|target| *target != ent)
+            .and_then(|target| physes.get(target))
+            .and_then(|phys| ps.location(phys))
+            .map(|location| location.xy());
+
+            let target_distance = target.map(|target| glm::distance(&target, &pos));
+            let home_distance = glm::distance(&home, &pos);
+            let time_in_state = ai.time_in_state;
+            let holds = |condition: &Condition| match *condition {
+                Condition::TargetWithin(d) => target_distance.map_or(false, |t| t <= d),
+                Condition::TargetBeyond(d) => target_distance.map_or(true, |t| t > d),
+                Condition::HealthBelow(f) => health.map_or(false, |h| h < f),
+                Condition::HealthAbove(f) => health.map_or(false, |h| h > f),
+                Condition::After(seconds) => time_in_state >= seconds,
+                Condition::AwayFromHome(d) => home_distance > d,
+                Condition::AtHome => home_distance <= ARRIVAL_DISTANCE,
+            };
+
+            // transitions to states that don't exist are never taken.
+            let next = ai
+                .state()
+                .and_then(|state| {
+                    state
+                        .transitions
+                        .iter()
+                        .find(|t| holds(&t.when) && ai.find_state(&t.to).is_some())
+                })
+                .map(|transition| transition.to.clone());
+            if let Some(next) = next {
+                if let Some(event) = ai.enter(&next).unwrap() {
+                    on_enter(event);
+                }
+            }
+
+            if listener == Some(ent) {
+                continue;
+            }
+            let (behaviour, speed) = match ai.state() {
+                Some(state) => (state.behaviour.clone(), state.speed),
+                None => continue,
+            };
+
+            // where they'd like to be, if anywhere.
+            let goal = match behaviour {
+                Behaviour::Idle => None,
+                Behaviour::Wander { radius } => {
+                    // a new spot once they've reached the last one, or given up on it.
+                    let arrived = ai.wander_goal.map_or(true, |(goal, tried)| {
+                        glm::distance(&glm::make_vec2(&goal), &pos) <= ARRIVAL_DISTANCE
+                            || tried >= WANDER_PATIENCE
+                    });
+                    if arrived {
+                        let spot = home
+                            + ApplyForce::random_2d_vec(&mut rng.0).xy()
+                                * radius
+                                * rng.0.gen::<f32>();
+                        ai.wander_goal = Some(([spot.x, spot.y], 0.0));
+                    }
+
+                    let (goal, tried) = ai.wander_goal.as_mut().unwrap();
+                    *tried += TICK_SECONDS;
+                    Some(glm::make_vec2(goal))
+                }
+                Behaviour::Follow { stop_distance } => {
+                    target.filter(|target| glm::distance(target, &pos) > stop_distance)
+                }
+                Behaviour::Flee { safe_distance } => target
+                    .filter(|target| glm::distance(target, &pos) < safe_distance)
+                    .map(|target| pos + (pos - target)),
+                Behaviour::ReturnHome => Some(home).filter(|_| home_distance > ARRIVAL_DISTANCE),
+            };
+
+            if let Some(goal) = goal {
+                let offset = goal - pos;
+                if glm::length(&offset) > 0.0 {
+                    let direction = offset.normalize();
+                    steer_body(
+                        ps.rigid_body_mut(phys).unwrap(),
+                        &glm::vec3(direction.x, direction.y, 0.0),
+                        speed,
                     );
                 }
             }
@@ -338,20 +1251,20 @@ impl<'a> System<'a> for EditorPlaceControls {
         ReadStorage<'a, Assemblaged>,
         Entities<'a>,
         ReadExpect<'a, LocalState>,
+        ReadExpect<'a, InputBindings>,
         ReadExpect<'a, Assemblager>,
     );
 
     fn run(
         &mut self,
-        (mut outlines, mut ps, mut compium, physes, asmblgd, ents, ls, asmblgr): Self::SystemData,
+        (mut outlines, mut ps, mut compium, physes, asmblgd, ents, ls, bindings, asmblgr): Self::SystemData,
     ) {
-        use winit::VirtualKeyCode::G;
         let mouse_clicked_this_frame = ls.last_input.mouse_state.unwrap_or(false);
         let new_mouse = &ls.last_input.mouse_pos;
 
         if let Some(ent) = compium.get_chosen_ent() {
             //toggle mouselock when they press G
-            if ls.tapped_keys.contains(&G) {
+            if bindings.tapped("editor_toggle_mouselock", &ls) {
                 compium.mouselock_chosen_ent = !compium.mouselock_chosen_ent;
             }
 
@@ -365,19 +1278,25 @@ impl<'a> System<'a> for EditorPlaceControls {
                         // get collision pos
                         let mouse_pos = new_mouse.unwrap_or(ls.mouse_pos);
                         let raycaster = Raycaster::point_from_camera(&mouse_pos, &ls);
-                        let ground_collision_pos = raycaster.cast_to_ground_pos(&ps).unwrap();
-
-                        // get its offset recorded in the type editor
-                        let Assemblaged { built_from } = asmblgd.get(ent).unwrap();
-                        let hitbox = asmblgr.assemblages[built_from]
-                            .iter()
-                            .find(|x| x.name() == "Hitbox")
-                            .unwrap()
-                            .downcast_ref::<Hitbox>()
-                            .unwrap();
-
-                        // set the location to the combination of the two
-                        ps.set_location(&phys, &(ground_collision_pos + hitbox.position));
+
+                        // get its offset recorded in the type editor,
+                        // things that weren't built from a type (or whose type has no hitbox)
+                        // just don't get an offset.
+                        let offset = asmblgd
+                            .get(ent)
+                            .and_then(|Assemblaged { built_from }| {
+                                asmblgr.assemblages.get(built_from)
+                            })
+                            .and_then(|components| components.iter().find(|x| x.name() == "Hitbox"))
+                            .and_then(|hitbox| hitbox.downcast_ref::<Hitbox>())
+                            .map(|hitbox| hitbox.position)
+                            .unwrap_or_else(glm::zero);
+
+                        // set the location to the combination of the two,
+                        // if they're actually pointing at the ground.
+                        if let Some(ground_collision_pos) = raycaster.cast_to_ground_pos(&ps) {
+                            ps.set_location(&phys, &(ground_collision_pos + offset));
+                        }
                     }
 
                     if mouse_clicked_this_frame {
@@ -391,19 +1310,16 @@ impl<'a> System<'a> for EditorPlaceControls {
         // they're probably trying to select something.
         if mouse_clicked_this_frame && !compium.mouselock_chosen_ent {
             let raycaster = Raycaster::point_from_camera(&ls.mouse_pos, &ls);
-            let clicked_body_handle = ps
+            let clicked_ent = ps
                 .world
                 .collider_world()
                 .interferences_with_ray(&raycaster.ray, &raycaster.collision_group)
                 .next()
-                .unwrap()
-                .0 //  it's (collider, rayhit), we want collider.body()
-                .body();
+                //  it's (collider, rayhit), we want collider.body()
+                .and_then(|(collider, _)| ps.body_entity(collider.body()));
 
-            if let Ok(id) =
-                serde_json::from_str(&ps.world.body(clicked_body_handle).unwrap().name())
-            {
-                compium.choose_ent(ents.entity(id), &ents, &mut outlines);
+            if let Some(ent) = clicked_ent {
+                compium.choose_ent(ent, &ents, &mut outlines);
             }
         }
     }
@@ -415,11 +1331,11 @@ impl<'a> System<'a> for EditorSave {
         ReadExpect<'a, Assemblager>,
         ReadExpect<'a, LazyUpdate>,
         ReadExpect<'a, LocalState>,
+        ReadExpect<'a, InputBindings>,
     );
 
-    fn run(&mut self, (asmblgr, lu, ls): Self::SystemData) {
-        use winit::VirtualKeyCode::{LControl, S};
-        if ls.last_input.keys_held.contains(&LControl) && ls.tapped_keys.contains(&S) {
+    fn run(&mut self, (asmblgr, lu, ls, bindings): Self::SystemData) {
+        if bindings.tapped("save", &ls) {
             asmblgr.save_json(&lu);
         }
     }
@@ -434,7 +1350,9 @@ impl<'a> System<'a> for PhysicsUpdate {
         WriteExpect<'a, PhysState>,
         ReadStorage<'a, Phys>,
         ReadStorage<'a, EmitCollideEvent>,
-        WriteStorage<'a, ScriptEvent>,
+        ReadStorage<'a, U64Marker>,
+        WriteExpect<'a, ScriptEvents>,
+        WriteExpect<'a, GameplayEvents>,
     );
 
     fn setup(&mut self, res: &mut specs::Resources) {
@@ -442,10 +1360,13 @@ impl<'a> System<'a> for PhysicsUpdate {
         self.reader_id = Some(WriteStorage::<Phys>::fetch(&res).register_reader());
     }
 
-    fn run(&mut self, (mut ps, physes, collides, mut script_events): Self::SystemData) {
+    fn run(
+        &mut self,
+        (mut ps, physes, collides, markers, mut script_events, mut gameplay_events): Self::SystemData,
+    ) {
         use nphysics3d::{
             math::{Force, ForceType},
-            object::{Body, BodyHandle},
+            object::Body,
         };
 
         for event in physes
@@ -454,23 +1375,24 @@ impl<'a> System<'a> for PhysicsUpdate {
         {
             match event {
                 ComponentEvent::Removed(id) => {
-                    let id_string = id.to_string();
-                    let handles = ps
-                        .world
-                        .bodies_with_name(&id_string)
-                        .map(|x| x.handle())
-                        .collect::<Vec<BodyHandle>>();
-                    ps.world.remove_bodies(&handles);
+                    if let Some(handle) = ps.entity_bodies.remove(id) {
+                        ps.previous_positions.remove(&handle);
+                        ps.world.remove_bodies(&[handle]);
+                    }
                 }
                 _ => (),
             }
         }
 
+        // what everything's velocity was before the step, to see how hard things hit each other.
+        let mut velocities_before = std::collections::HashMap::new();
+
         for handle in (&physes).join() {
             let timestep = ps.world.timestep();
             let body = ps.rigid_body_mut(handle).unwrap();
 
             let lv = &body.velocity().linear;
+            velocities_before.insert(handle.body, *lv);
             // perhaps replace that 0.5_f32 with a fraction of the actual velocity.
             let force = 2.0_f32.min(body.augmented_mass().linear * glm::length(lv) / timestep);
             let linear_force = -lv.normalize() * force;
@@ -478,6 +1400,11 @@ impl<'a> System<'a> for PhysicsUpdate {
             if force != 0.0 {
                 body.apply_force(0, &Force::linear(linear_force), ForceType::Force, true);
             }
+
+            // rendering happens between ticks, so it needs to know where things were
+            // before this step to smoothly move them to where they'll be after it.
+            let position = *body.position();
+            ps.previous_positions.insert(handle.body, position);
         }
 
         ps.world.step();
@@ -497,15 +1424,56 @@ impl<'a> System<'a> for PhysicsUpdate {
                     .and_then(|x| x.downcast_ref::<specs::Entity>())
                     .map(|x| x.clone());
                 if let (Some(ent_one), Some(ent_two)) = (ent_one, ent_two) {
+                    // scripts know entities by their persistent ids
+                    let (id_one, id_two) = match (markers.get(ent_one), markers.get(ent_two)) {
+                        (Some(one), Some(two)) => (one.id(), two.id()),
+                        _ => continue,
+                    };
+                    gameplay_events
+                        .0
+                        .push(GameplayEvent::Collided(id_one, id_two));
+
+                    // where they touched, if the contact's still around.
+                    let point = ps
+                        .world
+                        .collider_world()
+                        .contact_pair(handle_one, handle_two, true)
+                        .and_then(|(_, _, _, manifold)| manifold.deepest_contact())
+                        .map(|tracked| tracked.contact.world1.coords);
+
+                    // how much the collision changed an entity's momentum.
+                    let impulse = |ent: specs::Entity| -> f32 {
+                        physes
+                            .get(ent)
+                            .and_then(|phys| {
+                                let before = velocities_before.get(&phys.body)?;
+                                let body = ps.rigid_body(phys)?;
+                                Some(
+                                    glm::length(&(body.velocity().linear - before))
+                                        * body.augmented_mass().linear,
+                                )
+                            })
+                            .unwrap_or(0.0)
+                    };
+
+                    let payload = |ent: specs::Entity, other: u64| {
+                        let mut fields = vec![
+                            ("other", ScriptPayload::Entity(other)),
+                            ("impulse", ScriptPayload::Number(impulse(ent) as f64)),
+                        ];
+                        if let Some(point) = point.as_ref() {
+                            fields.push(("point", ScriptPayload::point(point)));
+                        }
+                        fields
+                    };
+
                     if let Some(EmitCollideEvent { script }) = collides.get(ent_one) {
                         script_events
-                            .insert(ent_one, script.clone_with_payload(ent_two.id()))
-                            .unwrap();
+                            .push(id_one, script.clone_with_payload(payload(ent_one, id_two)));
                     }
                     if let Some(EmitCollideEvent { script }) = collides.get(ent_two) {
                         script_events
-                            .insert(ent_two, script.clone_with_payload(ent_one.id()))
-                            .unwrap();
+                            .push(id_two, script.clone_with_payload(payload(ent_two, id_one)));
                     }
                 }
             }
@@ -555,47 +1523,67 @@ impl<'a> System<'a> for CameraLerp {
     }
 }
 
-fn main() {
-    use current::CurrentGuard;
-
-    simple_logger::init_with_level(log::Level::Debug).unwrap();
+// gameplay and physics run at a fixed rate, everything else runs once per rendered frame.
+struct Dispatchers {
+    tick: Dispatcher<'static, 'static>,
+    frame: Dispatcher<'static, 'static>,
+}
 
+// everything the simulation needs to run, whether or not there's a window around.
+fn build_world(
+    mut local_state: LocalState,
+    spritesheet_size: SpritesheetDimensions,
+    seed: u64,
+    slot: Option<String>,
+) -> (World, Dispatchers) {
     // -- Specs Resources:
-    // windowing stuff
-    let mut winit_state = WinitState::default();
-    let mut local_state = LocalState::from_winit_state(&winit_state);
     // Developer Tools stuff
-    let mut dev_ui = DevUiState::new(&winit_state.window);
     let compendium = Compendium::new();
-    // rendering
-    let (mut wgpu_state, spritesheet_size) = WgpuState::new(&winit_state.window, &mut dev_ui.imgui);
     // Dyon
-    let mut dyon_state = DyonState::new();
     let dyon_console = DyonConsole::default();
     // physics
     let physics_state = PhysState::new();
+    // anything that goes wrong loading content ends up here, instead of crashing the game
+    let mut load_errors = LoadErrors::default();
     // spritesheet texture indexes
-    let image_bundle = ImageBundle::new();
+    let image_bundle = ImageBundle::new(&mut load_errors);
 
     let mut world = World::new();
+    // these are run at a fixed rate, no matter how fast frames are being rendered.
     #[rustfmt::skip]
-    let mut dispatcher = DispatcherBuilder::new()
+    let mut tick = DispatcherBuilder::new()
         .with(AddHitboxesToPhys,            "hitboxes to phys",     &[])
         .with(ApplyForces,                  "apply forces",         &["hitboxes to phys"])
         .with(PhysicsUpdate::default(),     "physics update",       &["apply forces"])
-        .with(CameraLerp,                   "lerp camera",          &["physics update"])
         .with(Interact,                     "player interact",      &["physics update"])
         .with(KeyboardMovementControls,     "keyboard controls",    &["physics update"])
-        .with(EditorPlaceControls,          "editor place",         &["physics update"])
-        .with(EditorSave,                   "save world to file",   &["physics update"])
-        .with(Exploding,                    "explode effect",       &["physics update"])
+        .with(NpcAi,                        "npc ai",               &["physics update"])
+        .with(Attacking,                    "attacks",              &["physics update"])
+        .with(Projectiles,                  "projectiles",          &["physics update"])
+        .with(ApplyDamage,                  "apply damage",         &["attacks", "projectiles"])
+        .with(Regenerate,                   "regenerate",           &["apply damage"])
+        .with(Dying,                        "dying",                &["regenerate"])
+        .with(Exploding,                    "explode effect",       &["dying"])
+        .with(ScriptTimers,                 "script timers",        &["player interact"])
+        .with(QuestTracking,                "quest tracking",       &["script timers"])
+        .with(ItemPickup,                   "item pickup",          &["quest tracking"])
+        .with(PruneDyonCaches,              "prune dyon caches",    &[])
+        .build();
+
+    // and these are run once per rendered frame.
+    #[rustfmt::skip]
+    let mut frame = DispatcherBuilder::new()
+        .with(CameraLerp,                   "lerp camera",          &[])
+        .with(EditorPlaceControls,          "editor place",         &[])
+        .with(EditorSave,                   "save world to file",   &[])
         .with(BuildAppearances,             "builders to appears",  &[])
         .with(SpriteSheetAnimate,           "animate",              &["builders to appears"])
         .build();
 
-    dispatcher.setup(&mut world.res);
+    tick.setup(&mut world.res);
+    frame.setup(&mut world.res);
 
-    let mut assemblager = Assemblager::new();
+    let mut assemblager = Assemblager::new(&mut load_errors);
 
     macro_rules! register {
          ($($name:ty),+ $(,)?) => {$(
@@ -608,48 +1596,218 @@ fn main() {
     world.register::<Appearance>();
     world.register::<BoxOutline>();
     world.register::<Phys>();
+    world.register::<U64Marker>();
+    world.register::<Damage>();
+    world.register::<Detonate>();
+    world.add_resource(U64MarkerAllocator::new());
     register!(
         AppearanceBuilder,
         MovementControls,
         EmitCollideEvent,
+        Invulnerability,
+        AiBehaviour,
         ScriptingIds,
+        Projectile,
         Interactable,
+        Regeneration,
         ScriptEvent,
+        ScriptTick,
         Explodeable,
         CameraFocus,
         Animation,
+        Resistances,
+        Inventory,
+        OnDeath,
+        Attack,
         Hitbox,
         Health,
+        Item,
     );
 
-    assemblager.load_save(&mut world);
+    assemblager.slot = slot;
+    // the save has the dyon caches and script timers in it too
+    world.add_resource(DyonData::default());
+    world.add_resource(ScriptScheduler::default());
+    world.add_resource(QuestLog::default());
+    let save_header = assemblager.load_save(&mut world, &mut load_errors);
+    local_state.play_time = save_header.play_time;
     local_state.find_camera_focus_and_zoom(&world);
 
     world.add_resource(spritesheet_size);
     world.add_resource(physics_state);
     world.add_resource(image_bundle);
     world.add_resource(dyon_console);
+    world.add_resource(ScriptHandlers::default());
     world.add_resource(assemblager);
     world.add_resource(local_state);
     world.add_resource(compendium);
+    world.add_resource(DyonRepl::new());
+    world.add_resource(GameRng::new(seed));
+    world.add_resource(InputBindings::load(&mut load_errors));
+    world.add_resource(Dialogues::load(&mut load_errors));
+    world.add_resource(DialogueState::default());
+    world.add_resource(Quests::load(&mut load_errors));
+    world.add_resource(ItemDefinitions::load(&mut load_errors));
+    world.add_resource(GameplayEvents::default());
+    world.add_resource(ScriptEvents::default());
+    world.add_resource(load_errors);
+
+    // content files that get reloaded when they change, the save is watched once it's known.
+    let mut file_watcher = FileWatcher::new();
+    file_watcher.watch(TYPES_PATH);
+    file_watcher.watch(INDEXES_PATH);
+    file_watcher.watch(SPRITESHEET_PATH);
+    file_watcher.watch(DIALOGUE_PATH);
+    file_watcher.watch(QUESTS_PATH);
+    file_watcher.watch(ITEMS_PATH);
+    world.add_resource(file_watcher);
+
+    (world, Dispatchers { tick, frame })
+}
+
+// one fixed-length step of gameplay.
+fn tick(world: &mut World, dispatcher: &mut Dispatcher, dyon_state: &mut DyonState) {
+    use current::CurrentGuard;
+
+    // your everyday ECS systems are run first
+    dispatcher.dispatch(&mut world.res);
+
+    // the systems can add things that scripts might want
+    world.maintain();
+
+    // the scripting system completely breaks ECS
+    let specs_world_guard = CurrentGuard::new(world);
+    dyon_state.run();
+    drop(specs_world_guard);
+
+    // the scripts can add things lazily
+    world.maintain();
+
+    // everybody's had their chance to see what happened
+    world.write_resource::<GameplayEvents>().0.clear();
+    world.write_resource::<LocalState>().end_tick();
+}
+
+// one frame of the game, minus input and rendering.
+fn simulate(world: &mut World, dispatchers: &mut Dispatchers, dyon_state: &mut DyonState) {
+    // however many ticks fit into the time since the last frame are run,
+    // the leftover time is carried over into the next frame.
+    while world.write_resource::<LocalState>().start_tick() {
+        tick(world, &mut dispatchers.tick, dyon_state);
+    }
+
+    dispatchers.frame.dispatch(&mut world.res);
+    world.maintain();
+}
+
+// the value that follows a command line flag, if the flag was passed.
+fn arg_value<'a>(args: &'a [String], flag: &str) -> Option<&'a str> {
+    args.iter()
+        .position(|arg| arg == flag)
+        .and_then(|index| args.get(index + 1))
+        .map(|value| value.as_str())
+}
+
+fn main() {
+    simple_logger::init_with_level(log::Level::Debug).unwrap();
+
+    let args = std::env::args().collect::<Vec<_>>();
+
+    // replays bring their own seed, otherwise any seed will do.
+    let replay = arg_value(&args, "--replay")
+        .map(|path| InputReplay::open(path).expect("Couldn't read the replay file!"));
+    let seed = replay
+        .as_ref()
+        .map(|replay| replay.header.seed)
+        .unwrap_or_else(|| OsRng::new().unwrap().gen());
+
+    // no window, no GPU, just the simulation running as fast as it can.
+    if args.iter().any(|arg| arg == "--headless") {
+        let mut headless = Headless::new();
+        match replay {
+            Some(replay) => headless.replay(replay),
+            None => {
+                let frame_count = arg_value(&args, "--headless")
+                    .and_then(|frames| frames.parse().ok())
+                    .unwrap_or(headless::DEFAULT_FRAME_COUNT);
+                headless.run(std::iter::repeat(UserInput::default()).take(frame_count));
+            }
+        }
+        return;
+    }
+
+    // saves go in a slot in the user's data directory if there's one passed,
+    // otherwise it's straight to instances.json, for the level editing.
+    // a replay starts from whichever slot it was recorded in.
+    let slot = match &replay {
+        Some(replay) => {
+            let now = ReplayHeader::new(seed, replay.header.slot.clone());
+            for mismatch in replay.header.mismatches(&now) {
+                warn!("The replay might not reproduce the recording: {}", mismatch);
+            }
+            replay.header.slot.clone()
+        }
+        None => arg_value(&args, "--slot").map(String::from),
+    };
+
+    let mut replay = replay;
+    let mut recorder = arg_value(&args, "--record").map(|path| {
+        InputRecorder::create(path, &ReplayHeader::new(seed, slot.clone()))
+            .expect("Couldn't create the recording!")
+    });
+
+    // windowing stuff
+    let mut winit_state = WinitState::default();
+    let local_state = LocalState::from_winit_state(&winit_state);
+    // Developer Tools stuff
+    let mut dev_ui = DevUiState::new(&winit_state.window);
+    // rendering
+    let (mut wgpu_state, spritesheet_size) = WgpuState::new(&winit_state.window, &mut dev_ui.imgui);
+    // controllers, or a virtual one for testing the pad controls without one plugged in.
+    let mut gamepad_state = match arg_value(&args, "--virtual-gamepad") {
+        Some(path) => {
+            GamepadState::open_virtual_pad(path).expect("Couldn't read the virtual gamepad file!")
+        }
+        None => GamepadState::new(),
+    };
+    // Dyon
+    let mut dyon_state = DyonState::new();
+
+    let (mut world, mut dispatchers) = build_world(local_state, spritesheet_size, seed, slot);
 
     while !world.read_resource::<LocalState>().quit {
         // input deals with thread-bound stuff so it's not a system
-        winit_state.input(&world, &mut dev_ui);
-
-        // your everyday ECS systems are run first
-        dispatcher.dispatch(&mut world.res);
+        let mut input = winit_state.input(&mut dev_ui);
+        gamepad_state.poll(&mut input.gamepad);
+        input.dialogue_click = world.write_resource::<DialogueState>().clicked.take();
+
+        // while a replay's running, its frames are used instead of what the window says,
+        // (though you can still close the window)
+        if let Some(replayed) = replay.as_mut().and_then(InputReplay::next_frame) {
+            input = UserInput {
+                end_requested: input.end_requested || replayed.end_requested,
+                ..replayed
+            };
+        }
+        if let Some(recorder) = recorder.as_mut() {
+            recorder
+                .record(&input)
+                .expect("Couldn't write to the recording!");
+        }
+        world
+            .write_resource::<LocalState>()
+            .update_from_input(input);
 
-        // the systems can add things that scripts might want
-        world.maintain();
+        simulate(&mut world, &mut dispatchers, &mut dyon_state);
 
-        // the scripting system completely breaks ECS
-        let specs_world_guard = CurrentGuard::new(&mut world);
-        dyon_state.run();
-        drop(specs_world_guard);
+        // swap in whatever's been changed on disk
+        hot_reload(&mut world, &mut wgpu_state);
 
-        // the scripts can add things lazily
-        world.maintain();
+        // whatever they typed into the Dyon console last frame
+        let repl_line = world.write_resource::<DyonRepl>().pending.take();
+        if let Some(line) = repl_line {
+            dyon_state.repl(&mut world, &line);
+        }
 
         // next, the developer UI is generated based on all that.
         let ui = dev_ui.run(&world);
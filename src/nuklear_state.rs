@@ -1,62 +1,130 @@
 use crate::prelude::*;
-use nuklear_backend_wgpurs::Drawer;
-use nulkear::{
-    Allocator, FontConfig, FontAtlas, Handle,
+use nuklear::{
+    font_default_glyph_ranges, Allocator, AntiAliasing, Buffer as NkBuffer, Button, Context,
+    ConvertConfig, DrawNullTexture, FontAtlas, FontAtlasFormat, FontConfig, FontID, Handle, Vec2,
 };
+use nuklear_backend_wgpurs::Drawer;
+
+const MAX_VERTEX_MEMORY: usize = 512 * 1024;
+const MAX_ELEMENT_MEMORY: usize = 128 * 1024;
+const MAX_COMMANDS_MEMORY: usize = 64 * 1024;
 
+/// The game's own UI, the stuff players see. imgui is just for developers.
 pub struct NuklearState {
-    allo: Allocator,
-    font_cfg: FontCon
+    ctx: Context,
+    config: ConvertConfig,
+    drawer: Drawer,
+    // nuklear keeps pointers into these, so they have to stick around as long as the ctx does.
+    _media: Media,
+    _allo: Allocator,
 }
 
 impl NuklearState {
-    fn new() -> Self {
-        let allo = Allocator::new_vec();
+    pub fn new(device: &mut wgpu::Device) -> Self {
+        let mut allo = Allocator::new_vec();
+
+        let mut drawer = Drawer::new(
+            device,
+            wgpu::Color::BLACK,
+            36,
+            MAX_VERTEX_MEMORY,
+            MAX_ELEMENT_MEMORY,
+            NkBuffer::with_capacity(&mut allo, MAX_COMMANDS_MEMORY),
+        );
+        // the game's already been drawn by the time the UI is, so don't clear it away.
+        drawer.col = None;
+
+        let media = Media::new(&mut allo, &mut drawer, device);
+        let ctx = Context::new(
+            &mut allo,
+            media.font_atlas.font(media.font_16).unwrap().handle(),
+        );
+
+        let mut config = ConvertConfig::default();
+        config.set_null(media.null.clone());
+        config.set_circle_segment_count(22);
+        config.set_curve_segment_count(22);
+        config.set_arc_segment_count(22);
+        config.set_global_alpha(1.0);
+        // the font's pixel art, so it shouldn't be smoothed out.
+        config.set_shape_aa(AntiAliasing::Off);
+        config.set_line_aa(AntiAliasing::Off);
+
+        Self {
+            ctx,
+            config,
+            drawer,
+            _media: media,
+            _allo: allo,
+        }
     }
 
-    fn allocate_media(&mut self, drawer: &Drawer) {
+    // nuklear wants to be told about the mouse every frame.
+    fn input(&mut self, local_state: &LocalState) {
+        let (x, y) = (
+            local_state.mouse_pos.0 as i32,
+            local_state.mouse_pos.1 as i32,
+        );
+
+        self.ctx.input_begin();
+        self.ctx.input_motion(x, y);
+        self.ctx
+            .input_button(Button::Left, x, y, local_state.mouse_down);
+        self.ctx.input_end();
+    }
+
+    pub fn render(
+        &mut self,
+        world: &specs::World,
+        device: &mut wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+        (width, height): (u32, u32),
+    ) {
+        self.input(&world.read_resource::<LocalState>());
+
+        quest_tracker_ui(&mut self.ctx, world, width as f32);
+        dialogue_ui(&mut self.ctx, world, width as f32, height as f32);
+
+        self.drawer.draw(
+            &mut self.ctx,
+            &mut self.config,
+            encoder,
+            view,
+            device,
+            width,
+            height,
+            Vec2 { x: 1.0, y: 1.0 },
+        );
+        self.ctx.clear();
     }
 }
 
 pub struct Media {
     font_atlas: FontAtlas,
-    font_14: FontID,
-    font_18: FontID,
-    font_20: FontID,
-    font_22: FontID,
-
-    font_tex: Handle,
+    font_16: FontID,
+    null: DrawNullTexture,
+    _font_tex: Handle,
 }
 
 impl Media {
-    pub fn new(nk_state: &NuklearState, drawer: &Drawer) -> Self {
+    pub fn new(allo: &mut Allocator, drawer: &mut Drawer, device: &mut wgpu::Device) -> Self {
         let mut cfg = FontConfig::with_size(0.0);
-        cfg.set_oversample_h(3);
-        cfg.set_oversample_v(2);
-        cfg.set_glyph_range(font_cyrillic_glyph_ranges());
+        cfg.set_oversample_h(1);
+        cfg.set_oversample_v(1);
+        cfg.set_glyph_range(font_default_glyph_ranges());
         cfg.set_ttf(include_bytes!("./font/SDS_8x8.ttf"));
 
-        let mut atlas = FontAtlas::new(&mut nk_state.allo);
-
-        cfg.set_ttf_data_owned_by_atlas(false);
-        cfg.set_size(14_f32);
-        let font_14 = atlas.add_font_with_config(&cfg).unwrap();
-
-        cfg.set_ttf_data_owned_by_atlas(false);
-        cfg.set_size(18_f32);
-        let font_18 = atlas.add_font_with_config(&cfg).unwrap();
-
-        cfg.set_ttf_data_owned_by_atlas(false);
-        cfg.set_size(20_f32);
-        let font_20 = atlas.add_font_with_config(&cfg).unwrap();
+        let mut atlas = FontAtlas::new(allo);
 
+        // the font's 8x8, so it only looks right at multiples of 8.
         cfg.set_ttf_data_owned_by_atlas(false);
-        cfg.set_size(22_f32);
-        let font_22 = atlas.add_font_with_config(&cfg).unwrap();
+        cfg.set_size(16_f32);
+        let font_16 = atlas.add_font_with_config(&cfg).unwrap();
 
         let font_tex = {
             let (b, w, h) = atlas.bake(FontAtlasFormat::Rgba32);
-            drawer.add_texture(&mut device, b, w, h)
+            drawer.add_texture(device, b, w, h)
         };
 
         let mut null = DrawNullTexture::default();
@@ -65,11 +133,9 @@ impl Media {
 
         Self {
             font_atlas: atlas,
-            font_14,
-            font_18,
-            font_20,
-            font_22,
-            font_tex,
+            font_16,
+            null,
+            _font_tex: font_tex,
         }
     }
 }
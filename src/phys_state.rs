@@ -1,5 +1,5 @@
 use crate::prelude::*;
-use na::Vector3;
+use na::{Isometry3, Vector3};
 use nalgebra as na;
 use ncollide3d::{
     query::RayIntersection,
@@ -7,9 +7,10 @@ use ncollide3d::{
     world::{CollisionGroups, CollisionObjectHandle},
 };
 use nphysics3d::{
-    object::{Body, Collider, ColliderDesc, RigidBody, RigidBodyDesc},
+    object::{Body, BodyHandle, Collider, ColliderDesc, RigidBody, RigidBodyDesc},
     world::World,
 };
+use std::collections::HashMap;
 const GROUND_SIZE: f32 = 50.0;
 
 pub struct PhysState {
@@ -17,6 +18,10 @@ pub struct PhysState {
     pub can_collide_group: CollisionGroups,
     pub disabled_group: CollisionGroups,
     pub raycast_group: CollisionGroups,
+    // where each body was before the last step, so rendering can interpolate between ticks.
+    pub previous_positions: HashMap<BodyHandle, Isometry3<f32>>,
+    // which body belongs to which entity, so they can be cleaned up when the entity's Phys goes.
+    pub entity_bodies: HashMap<specs::world::Index, BodyHandle>,
 }
 
 #[allow(dead_code)]
@@ -43,6 +48,7 @@ impl PhysState {
         assert!(can_collide_group.can_interact_with_groups(&can_collide_group));*/
 
         let mut world = World::new();
+        world.set_timestep(TICK_SECONDS);
         let ground = ShapeHandle::new(Cuboid::new(Vector3::repeat(GROUND_SIZE)));
         ColliderDesc::new(ground)
             .collision_groups(can_collide_group)
@@ -54,6 +60,8 @@ impl PhysState {
             can_collide_group,
             disabled_group,
             raycast_group,
+            previous_positions: HashMap::new(),
+            entity_bodies: HashMap::new(),
         }
     }
 
@@ -91,11 +99,25 @@ impl PhysState {
         }
     }
 
+    /// Names the body after the entity's persistent id, if it has one,
+    /// and stores the entity itself as user data so it can be found from things like raycasts.
     #[inline]
-    pub fn name_as_ent(&mut self, phys: &Phys, ent_boxed: Box<specs::Entity>) {
+    pub fn name_as_ent(&mut self, phys: &Phys, ent: specs::Entity, id: Option<u64>) {
+        self.entity_bodies.insert(ent.id(), phys.body);
+
         let body = self.world.rigid_body_mut(phys.body).unwrap();
-        body.set_name((*ent_boxed).id().to_string().to_owned());
-        body.set_user_data(Some(ent_boxed));
+        body.set_name(id.map(|id| id.to_string()).unwrap_or_default());
+        body.set_user_data(Some(Box::new(ent)));
+    }
+
+    /// The entity a body belongs to, if any.
+    #[inline]
+    pub fn body_entity(&self, handle: BodyHandle) -> Option<specs::Entity> {
+        self.world
+            .rigid_body(handle)?
+            .user_data()?
+            .downcast_ref::<specs::Entity>()
+            .cloned()
     }
 
     #[inline]
@@ -123,6 +145,26 @@ impl PhysState {
         Some(&self.rigid_body(phys)?.position().translation.vector)
     }
 
+    /// The position of the body `alpha` of the way from where it was before the last tick
+    /// to where it is now.
+    #[inline]
+    pub fn interpolated_position(&self, phys: &Phys, alpha: f32) -> Option<Isometry3<f32>> {
+        use nalgebra::geometry::Translation3;
+
+        let current = self.rigid_body(phys)?.position();
+        Some(match self.previous_positions.get(&phys.body) {
+            Some(previous) => Isometry3::from_parts(
+                Translation3::from(glm::lerp(
+                    &previous.translation.vector,
+                    &current.translation.vector,
+                    alpha,
+                )),
+                previous.rotation.slerp(&current.rotation, alpha),
+            ),
+            None => *current,
+        })
+    }
+
     #[inline]
     pub fn euler_vec(&self, phys: &Phys) -> Option<Vector3<f32>> {
         let angles = self.rigid_body(phys)?.position().rotation.euler_angles();
@@ -142,6 +184,9 @@ impl PhysState {
 
     #[inline]
     pub fn set_location(&mut self, phys: &Phys, location: &Vector3<f32>) {
+        // it's being put somewhere new, not moving there, so there's nothing to interpolate.
+        self.previous_positions.remove(&phys.body);
+
         let rbd = self.rigid_body_mut(phys).unwrap();
         let mut position = rbd.position().clone();
         position.translation.vector = *location;
@@ -163,9 +208,52 @@ impl PhysState {
 
     #[inline]
     pub fn set_position(&mut self, phys: &Phys, location: &Vector3<f32>, rotation: &Vector3<f32>) {
+        self.previous_positions.remove(&phys.body);
         Self::position_body(self.rigid_body_mut(phys).unwrap(), location, rotation);
     }
 
+    /// Every entity whose body's center is within `range` of `center` on the ground,
+    /// and no more than half of `arc` (in radians) away from `facing`.
+    /// Comes with where each of them is, and the ground's never included.
+    pub fn entities_in_arc(
+        &self,
+        center: &Vector3<f32>,
+        facing: &glm::TVec2<f32>,
+        range: f32,
+        arc: f32,
+    ) -> Vec<(specs::Entity, Vector3<f32>)> {
+        use na::Point3;
+        use ncollide3d::bounding_volume::AABB;
+
+        let reach = Vector3::repeat(range);
+        let aabb = AABB::new(Point3::from(center - reach), Point3::from(center + reach));
+
+        let mut hits = self
+            .world
+            .collider_world()
+            .interferences_with_aabb(&aabb, &self.raycast_group)
+            .filter(|collider| !collider.body().is_ground())
+            .filter_map(|collider| {
+                let position = collider.position().translation.vector;
+                let offset = (position - center).xy();
+                let distance = offset.norm();
+
+                // right on top of them is always in the way of the swing.
+                let in_arc = distance == 0.0 || glm::angle(facing, &offset) <= arc / 2.0;
+                if distance <= range && in_arc {
+                    Some((self.body_entity(collider.body())?, position))
+                } else {
+                    None
+                }
+            })
+            .collect::<Vec<_>>();
+
+        // a body could have more than one collider
+        hits.sort_by_key(|(ent, _)| ent.id());
+        hits.dedup_by_key(|(ent, _)| ent.id());
+        hits
+    }
+
     #[inline]
     pub fn rbd_from_collider_handle(&self, h: &CollisionObjectHandle) -> Option<&RigidBody<f32>> {
         let body_handle = self.world.collider_body_handle(*h)?;
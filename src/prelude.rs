@@ -3,10 +3,26 @@ pub use crate::camera::*;
 pub use crate::compendium::*;
 pub use crate::comps::*;
 pub use crate::dev_ui::*;
+pub use crate::dialogue::*;
 pub use crate::dyon::*;
+pub use crate::dyon_repl::*;
+pub use crate::dyon_scripts::*;
+pub use crate::file_watcher::*;
+pub use crate::gamepad_state::*;
+pub use crate::gameplay_events::*;
+pub use crate::headless::*;
 pub use crate::image_bundle::*;
+pub use crate::input_bindings::*;
+pub use crate::items::*;
+pub use crate::load_errors::*;
 pub use crate::local_state::*;
+pub use crate::nuklear_state::*;
 pub use crate::phys_state::*;
+pub use crate::quests::*;
+pub use crate::replay::*;
+pub use crate::save_file::*;
+pub use crate::script_events::*;
+pub use crate::script_scheduler::*;
 pub use crate::user_input::*;
 pub use crate::wgpu::*;
 pub use crate::winit_state::*;
@@ -16,6 +32,7 @@ pub use wgpu::winit;
 pub use boolinator::Boolinator;
 pub use nalgebra_glm as glm;
 pub use rand::{prelude::*, rngs::OsRng};
+pub use specs::saveload::{Marker, MarkerAllocator, U64Marker, U64MarkerAllocator};
 
 #[allow(unused_imports)]
 pub use log::{debug, error, info, trace, warn};
@@ -0,0 +1,568 @@
+use crate::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+pub const QUESTS_PATH: &str = "./src/data/quests.json";
+
+fn one() -> u32 {
+    1
+}
+
+/// What has to happen for an objective to be done.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum Goal {
+    // entities with the scripting id `id` have to touch the player, or whatever has the
+    // scripting id `into` if there is one. Without a count, every last one of them has to.
+    Collect {
+        id: String,
+        #[serde(default)]
+        count: Option<u32>,
+        #[serde(default)]
+        into: Option<String>,
+    },
+    // the player has to interact with something that has the scripting id `id`.
+    TalkTo {
+        id: String,
+    },
+    // a script handler has to be called, some number of times.
+    Event {
+        function: String,
+        #[serde(default = "one")]
+        count: u32,
+    },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Objective {
+    // what the tracker shows the player
+    pub text: String,
+    pub goal: Goal,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct QuestStage {
+    pub description: String,
+    pub objectives: Vec<Objective>,
+}
+
+/// What the player gets once a quest is complete.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum QuestReward {
+    // called on the player, with the quest's name as the payload's `quest`.
+    Script { function: String },
+    Heal(f32),
+    MaxHealth(f32),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Quest {
+    pub title: String,
+    pub stages: Vec<QuestStage>,
+    #[serde(default)]
+    pub rewards: Vec<QuestReward>,
+}
+
+/// Every quest there is, keyed by the name scripts use for them.
+#[derive(Default, Debug, Clone)]
+pub struct Quests(pub HashMap<String, Quest>);
+
+impl Quests {
+    /// Quests without any stages can't be started, so they're left out and reported in `errors`.
+    pub fn load(errors: &mut LoadErrors) -> Self {
+        let mut quests: HashMap<String, Quest> = read_content_file(QUESTS_PATH, errors)
+            .and_then(|data| {
+                serde_json::from_str(&data)
+                    .map_err(|e| errors.push(LoadError::from_json_error(QUESTS_PATH, e)))
+                    .ok()
+            })
+            .unwrap_or_default();
+
+        quests.retain(|name, quest| {
+            if quest.stages.is_empty() {
+                errors.push(LoadError::new(
+                    QUESTS_PATH,
+                    name.as_str(),
+                    "Quest has no stages.",
+                ));
+            }
+            !quest.stages.is_empty()
+        });
+
+        Quests(quests)
+    }
+
+    fn get(&self, name: &str) -> Result<&Quest, String> {
+        self.0
+            .get(name)
+            .ok_or_else(|| format!("There's no quest called {}.", name))
+    }
+}
+
+/// The persistent ids of every entity with each scripting id,
+/// so objectives can tell how many of something are still left.
+pub fn scripting_id_index(
+    scripting_ids: &specs::ReadStorage<ScriptingIds>,
+    markers: &specs::ReadStorage<U64Marker>,
+) -> HashMap<String, Vec<u64>> {
+    use specs::Join;
+
+    let mut index: HashMap<String, Vec<u64>> = HashMap::new();
+    for (ScriptingIds { ids }, marker) in (scripting_ids, markers).join() {
+        for id in ids {
+            index.entry(id.clone()).or_default().push(marker.id());
+        }
+    }
+    index
+}
+
+/// An entity that was involved in a QuestSignal, as far as objectives care.
+pub struct QuestSubject<'a> {
+    pub id: u64,
+    pub scripting_ids: &'a [String],
+    pub is_player: bool,
+}
+
+impl QuestSubject<'_> {
+    fn has_id(&self, id: &str) -> bool {
+        self.scripting_ids.iter().any(|x| x == id)
+    }
+}
+
+/// Something that happened this tick that objectives might be waiting on.
+pub enum QuestSignal<'a> {
+    Touched(QuestSubject<'a>, QuestSubject<'a>),
+    TalkedTo(QuestSubject<'a>),
+    Handled(&'a str),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ObjectiveProgress {
+    #[serde(default)]
+    pub count: u32,
+    // the ids of what's been collected, so nothing's counted twice.
+    #[serde(default)]
+    pub seen: BTreeSet<u64>,
+}
+
+impl Goal {
+    fn notice(&self, progress: &mut ObjectiveProgress, signal: &QuestSignal) {
+        match (self, signal) {
+            (Goal::Collect { id, into, .. }, QuestSignal::Touched(one, two)) => {
+                for (item, collector) in [(one, two), (two, one)].iter() {
+                    let collects = match into {
+                        Some(into) => collector.has_id(into),
+                        None => collector.is_player,
+                    };
+                    if collects && item.has_id(id) {
+                        progress.seen.insert(item.id);
+                    }
+                }
+            }
+            (Goal::TalkTo { id }, QuestSignal::TalkedTo(with)) if with.has_id(id) => {
+                progress.count = 1;
+            }
+            (Goal::Event { function, .. }, QuestSignal::Handled(handled))
+                if function == handled =>
+            {
+                progress.count += 1;
+            }
+            _ => {}
+        }
+    }
+
+    // how many of the things to collect are still out there.
+    fn left(id: &str, progress: &ObjectiveProgress, index: &HashMap<String, Vec<u64>>) -> usize {
+        index.get(id).map_or(0, |ids| {
+            ids.iter().filter(|x| !progress.seen.contains(x)).count()
+        })
+    }
+
+    fn is_done(&self, progress: &ObjectiveProgress, index: &HashMap<String, Vec<u64>>) -> bool {
+        match self {
+            Goal::Collect {
+                count: Some(count), ..
+            } => progress.seen.len() as u32 >= *count,
+            Goal::Collect {
+                id, count: None, ..
+            } => Self::left(id, progress, index) == 0,
+            Goal::TalkTo { .. } => progress.count >= 1,
+            Goal::Event { count, .. } => progress.count >= *count,
+        }
+    }
+
+    // what goes after the objective's text in the tracker, i.e. "2/5"
+    fn progress_text(
+        &self,
+        progress: &ObjectiveProgress,
+        index: &HashMap<String, Vec<u64>>,
+    ) -> String {
+        match self {
+            Goal::Collect {
+                count: Some(count), ..
+            } => format!("{}/{}", (progress.seen.len() as u32).min(*count), count),
+            Goal::Collect {
+                id, count: None, ..
+            } => {
+                format!("{} left", Self::left(id, progress, index))
+            }
+            Goal::Event { count, .. } if *count > 1 => {
+                format!("{}/{}", progress.count.min(*count), count)
+            }
+            _ => String::new(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuestStatus {
+    NotStarted,
+    Active,
+    Complete,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct QuestProgress {
+    pub stage: usize,
+    pub objectives: Vec<ObjectiveProgress>,
+    #[serde(default)]
+    pub complete: bool,
+}
+
+/// What the tracker shows about a quest.
+pub struct TrackedQuest {
+    pub title: String,
+    pub stage: String,
+    // (text, progress, done)
+    pub objectives: Vec<(String, String, bool)>,
+}
+
+/// How far the player's gotten in every quest they've started.
+/// It's a resource so it can be saved with the world.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct QuestLog {
+    quests: BTreeMap<String, QuestProgress>,
+    // rewards for quests that were just completed, they're handed out on the next tick.
+    #[serde(skip)]
+    pending_rewards: Vec<(String, QuestReward)>,
+}
+
+impl QuestLog {
+    pub fn status(&self, name: &str) -> QuestStatus {
+        match self.quests.get(name) {
+            None => QuestStatus::NotStarted,
+            Some(progress) if progress.complete => QuestStatus::Complete,
+            Some(_) => QuestStatus::Active,
+        }
+    }
+
+    /// Which stage an active quest is on, starting from 0.
+    pub fn stage(&self, name: &str) -> Option<usize> {
+        self.quests
+            .get(name)
+            .filter(|progress| !progress.complete)
+            .map(|progress| progress.stage)
+    }
+
+    // moves a quest onto a stage, or completes it if there aren't that many stages.
+    fn enter_stage(&mut self, quest: &Quest, name: &str, stage: usize) {
+        let progress = match quest.stages.get(stage) {
+            Some(QuestStage { objectives, .. }) => QuestProgress {
+                stage,
+                objectives: vec![ObjectiveProgress::default(); objectives.len()],
+                complete: false,
+            },
+            None => {
+                info!("Quest complete: {}", quest.title);
+                self.pending_rewards.extend(
+                    quest
+                        .rewards
+                        .iter()
+                        .map(|reward| (name.to_owned(), reward.clone())),
+                );
+                QuestProgress {
+                    stage,
+                    objectives: Vec::new(),
+                    complete: true,
+                }
+            }
+        };
+        self.quests.insert(name.to_owned(), progress);
+    }
+
+    /// Returns false if the quest is already underway.
+    /// Quests that have been completed can be started again.
+    pub fn start(&mut self, quests: &Quests, name: &str) -> Result<bool, String> {
+        let quest = quests.get(name)?;
+        if self.status(name) == QuestStatus::Active {
+            return Ok(false);
+        }
+
+        self.enter_stage(quest, name, 0);
+        Ok(true)
+    }
+
+    /// Skips the rest of the current stage. Returns false if the quest isn't active.
+    pub fn advance(&mut self, quests: &Quests, name: &str) -> Result<bool, String> {
+        let quest = quests.get(name)?;
+        match self.stage(name) {
+            Some(stage) => {
+                self.enter_stage(quest, name, stage + 1);
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Skips every stage that's left. Returns false if the quest isn't active.
+    pub fn complete(&mut self, quests: &Quests, name: &str) -> Result<bool, String> {
+        let quest = quests.get(name)?;
+        if self.status(name) != QuestStatus::Active {
+            return Ok(false);
+        }
+
+        self.enter_stage(quest, name, quest.stages.len());
+        Ok(true)
+    }
+
+    /// Lets the objectives of every active quest see what's happened,
+    /// moving on to the next stage of any quest whose objectives are all done.
+    pub fn update(
+        &mut self,
+        quests: &Quests,
+        signals: &[QuestSignal],
+        index: &HashMap<String, Vec<u64>>,
+    ) {
+        let active = self
+            .quests
+            .iter()
+            .filter(|(_, progress)| !progress.complete)
+            .map(|(name, _)| name.clone())
+            .collect::<Vec<_>>();
+
+        for name in active {
+            // quests that were taken out of quests.json just sit there.
+            let quest = match quests.0.get(&name) {
+                Some(quest) => quest,
+                None => continue,
+            };
+
+            // only the stage that was going when the tick started gets to see what happened,
+            // but the ones after it could be done as soon as they start, so keep going until
+            // one isn't.
+            let mut signals = signals;
+            loop {
+                let progress = self.quests.get_mut(&name).unwrap();
+                let stage = match quest.stages.get(progress.stage) {
+                    Some(stage) if !progress.complete => stage,
+                    _ => break,
+                };
+
+                let mut done = true;
+                for (objective, objective_progress) in
+                    stage.objectives.iter().zip(progress.objectives.iter_mut())
+                {
+                    for signal in signals {
+                        objective.goal.notice(objective_progress, signal);
+                    }
+                    done &= objective.goal.is_done(objective_progress, index);
+                }
+
+                if !done {
+                    break;
+                }
+                let next = progress.stage + 1;
+                self.enter_stage(quest, &name, next);
+                signals = &[];
+            }
+        }
+    }
+
+    /// The rewards for quests that were completed since this was last called.
+    pub fn take_rewards(&mut self) -> Vec<(String, QuestReward)> {
+        std::mem::replace(&mut self.pending_rewards, Vec::new())
+    }
+
+    /// What's left to do in every active quest.
+    pub fn tracker(&self, quests: &Quests, index: &HashMap<String, Vec<u64>>) -> Vec<TrackedQuest> {
+        self.quests
+            .iter()
+            .filter(|(_, progress)| !progress.complete)
+            .filter_map(|(name, progress)| {
+                let quest = quests.0.get(name)?;
+                let stage = quest.stages.get(progress.stage)?;
+                Some(TrackedQuest {
+                    title: quest.title.clone(),
+                    stage: stage.description.clone(),
+                    objectives: stage
+                        .objectives
+                        .iter()
+                        .zip(progress.objectives.iter())
+                        .map(|(objective, objective_progress)| {
+                            (
+                                objective.text.clone(),
+                                objective.goal.progress_text(objective_progress, index),
+                                objective.goal.is_done(objective_progress, index),
+                            )
+                        })
+                        .collect(),
+                })
+            })
+            .collect()
+    }
+}
+
+/// Draws what's left to do in every active quest in the corner of the screen.
+pub fn quest_tracker_ui(ctx: &mut nuklear::Context, world: &specs::World, width: f32) {
+    use nuklear::{Flags, PanelFlags, Rect, TextAlignment};
+
+    let index = scripting_id_index(&world.read_storage(), &world.read_storage());
+    let tracked = world
+        .read_resource::<QuestLog>()
+        .tracker(&world.read_resource::<Quests>(), &index);
+    if tracked.is_empty() {
+        return;
+    }
+
+    let rows = tracked
+        .iter()
+        .map(|quest| 2 + quest.objectives.len())
+        .sum::<usize>();
+    let bounds = Rect {
+        x: width - 340.0,
+        y: 10.0,
+        w: 330.0,
+        h: 40.0 + rows as f32 * 22.0,
+    };
+    // it's just there to look at, clicks go right through it.
+    let flags =
+        PanelFlags::Border as Flags | PanelFlags::Title as Flags | PanelFlags::NoInput as Flags;
+
+    if ctx.begin("Quests".into(), bounds, flags) {
+        ctx.layout_row_dynamic(20.0, 1);
+        for quest in tracked {
+            ctx.text(&quest.title, TextAlignment::Left as Flags);
+            ctx.text(&quest.stage, TextAlignment::Left as Flags);
+            for (text, progress, done) in quest.objectives {
+                let check = if done { "[x]" } else { "[ ]" };
+                ctx.text(
+                    &format!("{} {} {}", check, text, progress),
+                    TextAlignment::Left as Flags,
+                );
+            }
+        }
+    }
+    ctx.end();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+
+    fn quests(stages: Vec<Value>) -> Quests {
+        let quest = serde_json::from_value(json!({
+            "title": "Test",
+            "stages": stages,
+            "rewards": [{ "Heal": 1.0 }]
+        }))
+        .unwrap();
+        Quests(vec![("test".to_owned(), quest)].into_iter().collect())
+    }
+
+    fn stage(goal: Value) -> Value {
+        json!({
+            "description": "",
+            "objectives": [{ "text": "", "goal": goal }]
+        })
+    }
+
+    fn subject(id: u64, scripting_ids: &[String], is_player: bool) -> QuestSubject {
+        QuestSubject {
+            id,
+            scripting_ids,
+            is_player,
+        }
+    }
+
+    #[test]
+    fn one_signal_finishes_one_stage() {
+        let quests = quests(vec![
+            stage(json!({ "TalkTo": { "id": "chicken" } })),
+            stage(json!({ "TalkTo": { "id": "chicken" } })),
+        ]);
+        let mut log = QuestLog::default();
+        log.start(&quests, "test").unwrap();
+
+        let chicken = vec!["chicken".to_owned()];
+        let talked = [QuestSignal::TalkedTo(subject(1, &chicken, false))];
+        log.update(&quests, &talked, &HashMap::new());
+        assert_eq!(log.stage("test"), Some(1));
+
+        log.update(&quests, &talked, &HashMap::new());
+        assert_eq!(log.status("test"), QuestStatus::Complete);
+        assert_eq!(log.take_rewards().len(), 1);
+        assert!(log.take_rewards().is_empty());
+    }
+
+    #[test]
+    fn stages_that_are_already_done_are_skipped() {
+        // there aren't any melons left, so there's nothing to collect.
+        let quests = quests(vec![
+            stage(json!({ "Event": { "function": "ring" } })),
+            stage(json!({ "Collect": { "id": "melon" } })),
+        ]);
+        let mut log = QuestLog::default();
+        log.start(&quests, "test").unwrap();
+
+        log.update(&quests, &[QuestSignal::Handled("ring")], &HashMap::new());
+        assert_eq!(log.status("test"), QuestStatus::Complete);
+    }
+
+    #[test]
+    fn collecting() {
+        let quests = quests(vec![stage(
+            json!({ "Collect": { "id": "melon", "count": 2 } }),
+        )]);
+        let mut log = QuestLog::default();
+        log.start(&quests, "test").unwrap();
+
+        let melon = vec!["melon".to_owned()];
+        let touched = |id| {
+            [QuestSignal::Touched(
+                subject(0, &[], true),
+                subject(id, &melon, false),
+            )]
+        };
+
+        // touching the same one twice only counts once.
+        log.update(&quests, &touched(1), &HashMap::new());
+        log.update(&quests, &touched(1), &HashMap::new());
+        assert_eq!(log.stage("test"), Some(0));
+
+        log.update(&quests, &touched(2), &HashMap::new());
+        assert_eq!(log.status("test"), QuestStatus::Complete);
+    }
+
+    #[test]
+    fn starting_and_skipping() {
+        let quests = quests(vec![
+            stage(json!({ "Event": { "function": "ring", "count": 3 } })),
+            stage(json!({ "Event": { "function": "ring" } })),
+        ]);
+        let mut log = QuestLog::default();
+        assert_eq!(log.status("test"), QuestStatus::NotStarted);
+        assert!(log.start(&quests, "not a quest").is_err());
+
+        assert_eq!(log.start(&quests, "test"), Ok(true));
+        assert_eq!(log.start(&quests, "test"), Ok(false));
+        assert_eq!(log.advance(&quests, "test"), Ok(true));
+        assert_eq!(log.stage("test"), Some(1));
+        assert_eq!(log.complete(&quests, "test"), Ok(true));
+        assert_eq!(log.status("test"), QuestStatus::Complete);
+        assert_eq!(log.advance(&quests, "test"), Ok(false));
+
+        // finished quests can be started over.
+        assert_eq!(log.start(&quests, "test"), Ok(true));
+        assert_eq!(log.stage("test"), Some(0));
+    }
+}
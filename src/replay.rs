@@ -0,0 +1,196 @@
+use crate::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::File;
+use std::io::{self, prelude::*, BufWriter};
+use std::path::PathBuf;
+
+// Recordings are a header line followed by one UserInput per line, all as JSON.
+// Everything the simulation does follows from those frames, the header's seed and the world
+// the header describes, so feeding them back in on top of that world reproduces the run exactly.
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ReplayHeader {
+    pub seed: u64,
+    // the save slot the run started from, none for the starting world.
+    #[serde(default)]
+    pub slot: Option<String>,
+    #[serde(default)]
+    pub save_version: u32,
+    // a hash of every content file the run depended on, by path.
+    // files that weren't there are left out.
+    #[serde(default)]
+    pub content: BTreeMap<String, u64>,
+}
+
+impl ReplayHeader {
+    /// Describes the world on disk right now, as a run starting from `slot` would see it.
+    pub fn new(seed: u64, slot: Option<String>) -> Self {
+        let content = content_files(slot.as_ref().map(String::as_str))
+            .into_iter()
+            .filter_map(|path| {
+                let contents = std::fs::read(&path).ok()?;
+                Some((path.to_string_lossy().into_owned(), hash(&contents)))
+            })
+            .collect();
+
+        Self {
+            seed,
+            slot,
+            save_version: SAVE_FORMAT_VERSION,
+            content,
+        }
+    }
+
+    /// Everything about the world `now` describes that's different from the one this was
+    /// recorded with. Any of them could make a replay go differently than the recording did.
+    pub fn mismatches(&self, now: &ReplayHeader) -> Vec<String> {
+        let mut mismatches = Vec::new();
+        if self.slot != now.slot {
+            mismatches.push(format!(
+                "It was recorded in slot {:?}, not {:?}.",
+                self.slot, now.slot
+            ));
+        }
+        if self.save_version != now.save_version {
+            mismatches.push(format!(
+                "It was recorded with save format version {}, not {}.",
+                self.save_version, now.save_version
+            ));
+        }
+
+        let paths = self
+            .content
+            .keys()
+            .chain(now.content.keys())
+            .collect::<BTreeSet<_>>();
+        for path in paths {
+            if self.content.get(path) != now.content.get(path) {
+                mismatches.push(format!("{} has changed since it was recorded.", path));
+            }
+        }
+        mismatches
+    }
+}
+
+// everything a run depends on besides its input and its seed.
+fn content_files(slot: Option<&str>) -> Vec<PathBuf> {
+    let mut files = vec![instances_path_for(slot)];
+    files.extend(
+        [
+            TYPES_PATH,
+            DIALOGUE_PATH,
+            QUESTS_PATH,
+            ITEMS_PATH,
+            BINDINGS_PATH,
+        ]
+        .iter()
+        .map(PathBuf::from),
+    );
+
+    let mut scripts = std::fs::read_dir(SCRIPTS_DIRECTORY)
+        .map(|dir| {
+            dir.filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    scripts.sort();
+    files.extend(scripts);
+    files
+}
+
+// FNV-1a, std's hashers aren't guaranteed to hash the same way from one build to the next.
+fn hash(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// All of the game's randomness should come from here, so that replays can reproduce it.
+pub struct GameRng(pub StdRng);
+impl GameRng {
+    pub fn new(seed: u64) -> Self {
+        GameRng(StdRng::seed_from_u64(seed))
+    }
+}
+
+pub struct InputRecorder {
+    file: BufWriter<File>,
+}
+impl InputRecorder {
+    pub fn create(path: &str, header: &ReplayHeader) -> io::Result<Self> {
+        let mut recorder = Self {
+            file: BufWriter::new(File::create(path)?),
+        };
+        recorder.write_line(header)?;
+        Ok(recorder)
+    }
+
+    /// Writes a frame out right away, so a crash still leaves a recording of what led up to it.
+    pub fn record(&mut self, input: &UserInput) -> io::Result<()> {
+        self.write_line(input)?;
+        self.file.flush()
+    }
+
+    fn write_line<T: Serialize>(&mut self, value: &T) -> io::Result<()> {
+        serde_json::to_writer(&mut self.file, value)?;
+        self.file.write_all(b"\n")
+    }
+}
+
+pub struct InputReplay {
+    pub header: ReplayHeader,
+    frames: std::vec::IntoIter<UserInput>,
+}
+impl InputReplay {
+    pub fn open(path: &str) -> io::Result<Self> {
+        let mut contents = String::new();
+        File::open(path)?.read_to_string(&mut contents)?;
+        let mut lines = contents.lines();
+
+        let header = serde_json::from_str(lines.next().unwrap_or(""))?;
+        let frames = lines
+            .map(serde_json::from_str)
+            .collect::<Result<Vec<UserInput>, _>>()?;
+
+        Ok(Self {
+            header,
+            frames: frames.into_iter(),
+        })
+    }
+
+    pub fn next_frame(&mut self) -> Option<UserInput> {
+        self.frames.next()
+    }
+}
+
+impl Iterator for InputReplay {
+    type Item = UserInput;
+
+    fn next(&mut self) -> Option<UserInput> {
+        self.next_frame()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mismatches() {
+        let recorded = ReplayHeader::new(0, None);
+        assert!(recorded.mismatches(&ReplayHeader::new(0, None)).is_empty());
+
+        let mut now = recorded.clone();
+        now.save_version += 1;
+        now.content
+            .insert(TYPES_PATH.to_string(), recorded.content[TYPES_PATH] + 1);
+        now.content.insert("./new.dyon".to_string(), 0);
+        assert_eq!(recorded.mismatches(&now).len(), 3);
+
+        // recordings from before there was anything but the seed still open, just with warnings.
+        let old: ReplayHeader = serde_json::from_str("{\"seed\":0}").unwrap();
+        assert!(!old.mismatches(&recorded).is_empty());
+    }
+}
@@ -0,0 +1,222 @@
+use crate::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Turns a save in one version of the format into the next version, in place.
+/// The save is the whole `{"header": ..., "entities": ...}` object.
+pub type Migration = fn(&mut Value);
+
+/// `MIGRATIONS[n]` upgrades a version `n` save to version `n + 1`.
+/// When you change how a component is saved, bump the format by adding one here,
+/// that way everybody's old saves (and instances.json!) still load.
+const MIGRATIONS: [Migration; 3] = [
+    // version 0 was a bare array of entities. wrapping it in a header is all it takes,
+    // and that's already done by the time the migrations are run.
+    |_| {},
+    // version 2 gave each entity a persistent id. the dyon caches were keyed by
+    // where the entity was in the save, so using that as the id keeps them lined up.
+    |save| {
+        if let Value::Array(entities) = &mut save["entities"] {
+            for (index, components) in entities.iter_mut().enumerate() {
+                *components = json!({ "id": index, "components": components.take() });
+            }
+        }
+    },
+    // version 3 made script payloads structured values instead of just a number.
+    // the numbers were always entity ids, or 0 when there wasn't anything to pass along.
+    |save| {
+        fn upgrade(script: &mut Value) {
+            if let Some(id) = script.get("payload").and_then(Value::as_u64) {
+                script["payload"] = match id {
+                    0 => json!("None"),
+                    id => json!({ "Entity": id }),
+                };
+            }
+        }
+
+        for_each_component(save, "ScriptEvent", upgrade);
+        for_each_component(save, "Interactable", |c| upgrade(&mut c["script"]));
+        for_each_component(save, "EmitCollideEvent", |c| upgrade(&mut c["script"]));
+    },
+];
+
+pub const SAVE_FORMAT_VERSION: u32 = MIGRATIONS.len() as u32;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SaveHeader {
+    pub version: u32,
+    // seconds since the unix epoch when the save was written
+    pub timestamp: u64,
+    // total seconds played across every session that went into this save
+    pub play_time: f32,
+}
+
+impl SaveHeader {
+    pub fn new(play_time: f32) -> Self {
+        Self {
+            version: SAVE_FORMAT_VERSION,
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|since| since.as_secs())
+                .unwrap_or(0),
+            play_time,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SavedEntity {
+    // the entity's U64Marker, which it keeps from save to save.
+    pub id: u64,
+    // an array of the components' json,
+    // it's left as json here so the Assemblager can deal with it.
+    pub components: Value,
+}
+
+/// Everything in a save, once it's been brought up to the current version.
+#[derive(Deserialize, Debug)]
+pub struct SaveFile {
+    pub header: SaveHeader,
+    // these are each a SavedEntity, they're parsed one by one so a broken one can be skipped.
+    pub entities: Vec<Value>,
+    // the Dyon caches, keyed by the id of the entity they belong to.
+    #[serde(default)]
+    pub dyon_caches: HashMap<u64, Value>,
+    // what the scripts are waiting on.
+    #[serde(default)]
+    pub script_timers: ScriptScheduler,
+    // how far along the player is in their quests.
+    #[serde(default)]
+    pub quest_log: QuestLog,
+}
+
+/// Where all of the save slots go.
+pub fn save_directory() -> PathBuf {
+    dirs::data_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("stockRPG")
+        .join("saves")
+}
+
+pub fn slot_path(slot: &str) -> PathBuf {
+    save_directory().join(format!("{}.json", slot))
+}
+
+/// The names of all of the slots that have been saved to so far.
+pub fn list_slots() -> Vec<String> {
+    std::fs::read_dir(save_directory())
+        .map(|dir| {
+            dir.filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .filter(|path| path.extension().map_or(false, |ext| ext == "json"))
+                .filter_map(|path| Some(path.file_stem()?.to_string_lossy().to_string()))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Brings a save of any version up to the current one.
+/// Only the overall shape of the save is checked here, the entities are left for the Assemblager.
+pub fn migrate(mut save: Value) -> Result<SaveFile, String> {
+    // bare arrays are from before there were headers
+    if save.is_array() {
+        save = json!({
+            "header": { "version": 0, "timestamp": 0, "play_time": 0.0 },
+            "entities": save,
+        });
+    }
+
+    let version = save["header"]["version"]
+        .as_u64()
+        .ok_or("Save file header has no version!")? as usize;
+    if version > MIGRATIONS.len() {
+        return Err(format!(
+            "Save file is from version {} of the format, but only up to {} is supported!",
+            version, SAVE_FORMAT_VERSION
+        ));
+    }
+
+    for (from, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+        info!("Migrating save file from version {} to {}", from, from + 1);
+        migration(&mut save);
+        save["header"]["version"] = json!(from + 1);
+    }
+
+    serde_json::from_value(save).map_err(|e| format!("Save file doesn't look like a save: {}", e))
+}
+
+/// Calls `f` on the data of every component with the given name in the save,
+/// for migrations that change how a component looks.
+/// Entities only have their components split out from version 2 on, so it's no use before that.
+pub fn for_each_component<F: FnMut(&mut Value)>(save: &mut Value, name: &str, mut f: F) {
+    if let Value::Array(entities) = &mut save["entities"] {
+        for entity in entities {
+            if let Value::Array(components) = &mut entity["components"] {
+                for component in components {
+                    if let Some(data) = component.get_mut(name) {
+                        f(data);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use specs::Join;
+
+    // the starting world from back before saves had headers.
+    const SAVE_V0_PATH: &str = "./tests/fixtures/save_v0.json";
+
+    fn save_v0() -> Value {
+        serde_json::from_str(&std::fs::read_to_string(SAVE_V0_PATH).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn migrate_v0() {
+        let entity_count = save_v0().as_array().unwrap().len();
+        let save = migrate(save_v0()).unwrap();
+
+        assert_eq!(save.header.version, SAVE_FORMAT_VERSION);
+        assert_eq!(save.entities.len(), entity_count);
+        for (index, entity) in save.entities.into_iter().enumerate() {
+            let SavedEntity { id, components } = serde_json::from_value(entity).unwrap();
+            assert_eq!(id, index as u64);
+
+            // the payloads were all 0, which is nothing now.
+            for component in components.as_array().unwrap() {
+                for name in &["Interactable", "EmitCollideEvent"] {
+                    if let Some(data) = component.get(name) {
+                        assert_eq!(data["script"]["payload"], json!("None"));
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn load_v0() {
+        let mut headless = Headless::new();
+        let entity_count = save_v0().as_array().unwrap().len();
+
+        headless.world.write_resource::<LocalState>().play_time = 100.0;
+
+        let mut errors = LoadErrors::default();
+        let header = reload_instances(&mut headless.world, SAVE_V0_PATH.into(), &mut errors);
+        assert!(errors.0.is_empty(), "{:?}", errors.0);
+        // version 0 didn't keep track of play time, so it starts over.
+        assert_eq!(header.play_time, 0.0);
+        assert_eq!(headless.world.read_resource::<LocalState>().play_time, 0.0);
+
+        let world = &headless.world;
+        let loaded = (&world.read_storage::<Assemblaged>(), &world.entities())
+            .join()
+            .count();
+        assert_eq!(loaded, entity_count);
+    }
+}
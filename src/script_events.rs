@@ -0,0 +1,18 @@
+use crate::prelude::*;
+
+/// Script handlers that are waiting to be run, with the persistent id of the entity each is for.
+/// An entity only has room for one ScriptEvent component, so anything that can happen more than
+/// once a tick goes in here instead, where nothing gets overwritten.
+/// They're all run in order once the tick's systems are done, and anything that the scripts
+/// add while they're running waits for the next tick.
+#[derive(Default, Debug)]
+pub struct ScriptEvents(pub Vec<(u64, ScriptEvent)>);
+
+impl ScriptEvents {
+    /// Events without a handler, like from an Interactable that's just an item, are dropped.
+    pub fn push(&mut self, id: u64, event: ScriptEvent) {
+        if !event.function.is_empty() {
+            self.0.push((id, event));
+        }
+    }
+}
@@ -0,0 +1,94 @@
+use crate::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// A script handler that's waiting to be called on an entity.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ScriptTimer {
+    pub function: String,
+    // the persistent id of the entity the handler is called on.
+    pub entity: u64,
+    // seconds until it goes off.
+    pub remaining: f32,
+    // if it goes off over and over, how many seconds it waits between each time.
+    pub repeat: Option<f32>,
+}
+
+/// Lets scripts call handlers some time in the future, instead of right away.
+/// When a timer goes off, a ScriptEvent is queued up for its entity,
+/// with the timer's handle as the payload so repeating timers can cancel themselves.
+/// It's a resource so it can be saved with the world, timers and all.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ScriptScheduler {
+    next_handle: u64,
+    timers: BTreeMap<u64, ScriptTimer>,
+}
+
+impl ScriptScheduler {
+    fn schedule(&mut self, timer: ScriptTimer) -> u64 {
+        let handle = self.next_handle;
+        self.next_handle += 1;
+        self.timers.insert(handle, timer);
+        handle
+    }
+
+    /// Calls `function` on `entity` once, `seconds` from now.
+    pub fn after(&mut self, seconds: f32, function: String, entity: u64) -> u64 {
+        self.schedule(ScriptTimer {
+            function,
+            entity,
+            remaining: seconds,
+            repeat: None,
+        })
+    }
+
+    /// Calls `function` on `entity` every `seconds`, until it's cancelled.
+    pub fn every(&mut self, seconds: f32, function: String, entity: u64) -> u64 {
+        // a repeating timer that never waits would go off forever in one tick.
+        let seconds = seconds.max(TICK_SECONDS);
+        self.schedule(ScriptTimer {
+            function,
+            entity,
+            remaining: seconds,
+            repeat: Some(seconds),
+        })
+    }
+
+    /// Returns whether or not there was a timer to cancel.
+    pub fn cancel(&mut self, handle: u64) -> bool {
+        self.timers.remove(&handle).is_some()
+    }
+
+    /// Counts every timer down, returning the (handle, timer) of each that went off.
+    /// Timers that don't repeat are forgotten once they've gone off.
+    pub fn advance(&mut self, seconds: f32) -> Vec<(u64, ScriptTimer)> {
+        let mut fired = Vec::new();
+
+        for (handle, timer) in self.timers.iter_mut() {
+            timer.remaining -= seconds;
+            if timer.remaining <= 0.0 {
+                fired.push((*handle, timer.clone()));
+                if let Some(repeat) = timer.repeat {
+                    timer.remaining += repeat;
+                }
+            }
+        }
+
+        self.timers.retain(|_, timer| timer.remaining > 0.0);
+        fired
+    }
+
+    /// A copy with only the timers for entities that `is_saved`,
+    /// since the rest won't have anything to go off on after a load.
+    pub fn saved<F: Fn(u64) -> bool>(&self, is_saved: F) -> Self {
+        Self {
+            next_handle: self.next_handle,
+            timers: self
+                .timers
+                .iter()
+                .filter(|(_, timer)| is_saved(timer.entity))
+                .map(|(handle, timer)| (*handle, timer.clone()))
+                .collect(),
+        }
+    }
+}
@@ -1,20 +1,41 @@
 use crate::prelude::*;
+use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
 use winit::{ElementState, KeyboardInput, MouseButton, VirtualKeyCode, WindowEvent};
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct UserInput {
     pub end_requested: bool,
     pub new_frame_size: Option<(f64, f64)>,
     pub keys_held: HashSet<VirtualKeyCode>,
     pub mouse_pos: Option<(f32, f32)>,
     pub mouse_state: Option<bool>,
+    pub mouse_buttons_held: HashSet<MouseButton>,
+    pub gamepad: GamepadInput,
+    // how long this frame took
     pub seconds: f32,
     pub focus: Option<bool>,
+    // what was clicked in the dialogue window last frame.
+    #[serde(default)]
+    pub dialogue_click: Option<DialogueClick>,
 }
 
 impl UserInput {
-    pub fn process_event(&mut self, event: &WindowEvent, keys_held: &mut HashSet<VirtualKeyCode>) {
+    /// Handy for scripting input frames, e.g. for headless tests.
+    #[cfg(test)]
+    pub fn with_keys_held(keys: &[VirtualKeyCode]) -> Self {
+        Self {
+            keys_held: keys.iter().cloned().collect(),
+            ..Self::default()
+        }
+    }
+
+    pub fn process_event(
+        &mut self,
+        event: &WindowEvent,
+        keys_held: &mut HashSet<VirtualKeyCode>,
+        mouse_buttons_held: &mut HashSet<MouseButton>,
+    ) {
         // now we actually poll those events
         match event {
             // Close when asked
@@ -51,26 +72,22 @@ impl UserInput {
                 self.mouse_pos = Some((*x as f32, *y as f32));
             }
 
-            WindowEvent::MouseInput {
-                state: ElementState::Pressed,
-                button: MouseButton::Left,
-                ..
-            } => {
-                self.mouse_state = Some(true);
+            WindowEvent::MouseInput { state, button, .. } => {
+                // the left button is special, clicking it is how you select and place things.
+                if *button == MouseButton::Left {
+                    self.mouse_state = Some(*state == ElementState::Pressed);
+                }
+
+                match state {
+                    ElementState::Pressed => mouse_buttons_held.insert(*button),
+                    ElementState::Released => mouse_buttons_held.remove(button),
+                };
             }
 
             WindowEvent::Focused(focus_state) => {
                 self.focus = Some(*focus_state);
             }
 
-            WindowEvent::MouseInput {
-                state: ElementState::Released,
-                button: MouseButton::Left,
-                ..
-            } => {
-                self.mouse_state = Some(false);
-            }
-
             // Update our size info if the window changes size.
             WindowEvent::Resized(logical) => {
                 self.new_frame_size = Some((logical.width, logical.height));
@@ -7,6 +7,20 @@ pub struct SpritesheetDimensions {
     pub y: f32,
 }
 
+impl SpritesheetDimensions {
+    /// Reads the dimensions straight out of the spritesheet, for when there's no GPU around
+    /// to hand the texture to.
+    pub fn from_spritesheet() -> Self {
+        let texels = image::load_from_memory(IMG_BYTES)
+            .expect("Binary corrupted!")
+            .to_rgba();
+        Self {
+            x: texels.dimensions().0 as f32,
+            y: texels.dimensions().1 as f32,
+        }
+    }
+}
+
 #[derive(Clone, Copy)]
 pub struct SpritesheetVertex {
     _pos: [f32; 4],
@@ -26,11 +40,12 @@ impl SpritesheetVertex {
         let physes = world.read_storage::<Phys>();
         let appears = world.read_storage::<Appearance>();
         let ps = world.read_resource::<PhysState>();
+        let alpha = world.read_resource::<LocalState>().tick_alpha();
 
         (&appears, &physes)
             .join()
             .map(|(Appearance { size, uvs: uv }, phys)| {
-                let iso = ps.rigid_body(phys).unwrap().position();
+                let iso = &ps.interpolated_position(phys, alpha).unwrap();
 
                 #[cfg_attr(rustfmt, rustfmt_skip)]
                 vec![
@@ -85,12 +100,13 @@ impl BoxOutlineVertex {
         let physes = world.read_storage::<Phys>();
         let outlines = world.read_storage::<BoxOutline>();
         let ps = world.read_resource::<PhysState>();
+        let alpha = world.read_resource::<LocalState>().tick_alpha();
 
         let mut all_outlines = Vec::new();
 
         for (phys, bo) in (&physes, &outlines).join() {
             let s = ps.scale(phys).unwrap();
-            let iso = ps.rigid_body(phys).unwrap().position();
+            let iso = &ps.interpolated_position(phys, alpha).unwrap();
 
             //input color
             let npt = bo.color;
@@ -141,4 +157,60 @@ impl BoxOutlineVertex {
 
         all_outlines
     }
+
+    /// A bar above everything with Health, split into how much they've got and how much they're
+    /// missing. Each part is its own quad, with no hole so it's filled in.
+    pub fn health_bars_from_ecs(world: &World) -> Vec<Vec<Self>> {
+        const GAP: f32 = 4.0 / 64.0;
+        const THICKNESS: f32 = 3.0 / 64.0;
+        const FILLED: [f32; 3] = [0.2, 0.8, 0.2];
+        const EMPTY: [f32; 3] = [0.4, 0.05, 0.05];
+
+        let physes = world.read_storage::<Phys>();
+        let appears = world.read_storage::<Appearance>();
+        let healths = world.read_storage::<Health>();
+        let ps = world.read_resource::<PhysState>();
+        let alpha = world.read_resource::<LocalState>().tick_alpha();
+
+        let mut all_bars = Vec::new();
+
+        for (Appearance { size, .. }, health, phys) in (&appears, &healths, &physes).join() {
+            if health.max <= 0.0 {
+                continue;
+            }
+            let iso = &ps.interpolated_position(phys, alpha).unwrap();
+
+            let ratio = (health.value / health.max).max(0.0).min(1.0);
+            let split = -size[0] + 2.0 * size[0] * ratio;
+            let (near, far) = (size[1] + GAP, size[1] + GAP + THICKNESS);
+
+            for &(left, right, rgb) in [(-size[0], split, FILLED), (split, size[0], EMPTY)].iter() {
+                // there's nothing to draw of a full or empty part
+                if right <= left {
+                    continue;
+                }
+
+                #[cfg_attr(rustfmt, rustfmt_skip)]
+                let quad = [
+                    //top left
+                    [left,  0.0, near],
+                    //bottom left
+                    [left,  0.0, far],
+                    //top right
+                    [right, 0.0, near],
+                    //bottom right
+                    [right, 0.0, far],
+                ];
+
+                all_bars.push(
+                    quad.iter()
+                        .map(|loc| SpritesheetVertex::new(iso, loc, &[0.0, 0.0]))
+                        .map(|ss_vert| Self::from_ss_vert(ss_vert, [0.0, 0.0], rgb))
+                        .collect::<Vec<_>>(),
+                );
+            }
+        }
+
+        all_bars
+    }
 }
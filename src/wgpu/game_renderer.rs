@@ -14,6 +14,9 @@ pub struct GameRenderer {
     index_count: usize,
     uniform_buf: wgpu::Buffer,
     spritesheet_mat: Material,
+    // these are kept around so the spritesheet can be swapped out when it changes.
+    spritesheet_layout: wgpu::BindGroupLayout,
+    spritesheet_sampler: wgpu::Sampler,
     box_outline_mat: Material,
     depth: wgpu::TextureView,
     pub clear_color: Color,
@@ -43,7 +46,7 @@ impl GameRenderer {
             )
             .fill_from_slice(&helper::cast_slice(mx_ref));
 
-        let spritesheet_mat = {
+        let (spritesheet_mat, spritesheet_layout, spritesheet_sampler) = {
             // Create pipeline layout
             let bind_group_layout =
                 device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
@@ -69,46 +72,8 @@ impl GameRenderer {
                 bind_group_layouts: &[&bind_group_layout],
             });
 
-            let texture_extent = wgpu::Extent3d {
-                width: texels.width(),
-                height: texels.height(),
-                depth: 1,
-            };
-            let texture = device.create_texture(&wgpu::TextureDescriptor {
-                size: texture_extent,
-                array_layer_count: 1,
-                mip_level_count: 1,
-                sample_count: 1,
-                dimension: wgpu::TextureDimension::D2,
-                format: wgpu::TextureFormat::Rgba8Unorm,
-                usage: wgpu::TextureUsage::SAMPLED | wgpu::TextureUsage::TRANSFER_DST,
-            });
-            let texture_view = texture.create_default_view();
-            let temp_buf = device
-                .create_buffer_mapped(texels.len(), wgpu::BufferUsage::TRANSFER_SRC)
-                .fill_from_slice(&texels);
-            init_encoder.copy_buffer_to_texture(
-                wgpu::BufferCopyView {
-                    buffer: &temp_buf,
-                    offset: 0,
-                    row_pitch: 4 * texels.width(),
-                    image_height: texels.height(),
-                },
-                wgpu::TextureCopyView {
-                    texture: &texture,
-                    mip_level: 0,
-                    array_layer: 0,
-                    origin: wgpu::Origin3d {
-                        x: 0.0,
-                        y: 0.0,
-                        z: 0.0,
-                    },
-                },
-                texture_extent,
-            );
-
             // Create other resources
-            let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            let spritesheet_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
                 address_mode_u: wgpu::AddressMode::ClampToEdge,
                 address_mode_v: wgpu::AddressMode::ClampToEdge,
                 address_mode_w: wgpu::AddressMode::ClampToEdge,
@@ -120,27 +85,14 @@ impl GameRenderer {
                 compare_function: wgpu::CompareFunction::Always,
             });
 
-            // Create bind group
-            let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-                layout: &bind_group_layout,
-                bindings: &[
-                    wgpu::Binding {
-                        binding: 0,
-                        resource: wgpu::BindingResource::Buffer {
-                            buffer: &uniform_buf,
-                            range: 0..64,
-                        },
-                    },
-                    wgpu::Binding {
-                        binding: 1,
-                        resource: wgpu::BindingResource::TextureView(&texture_view),
-                    },
-                    wgpu::Binding {
-                        binding: 2,
-                        resource: wgpu::BindingResource::Sampler(&sampler),
-                    },
-                ],
-            });
+            let bind_group = Self::spritesheet_bind_group(
+                device,
+                &mut init_encoder,
+                &bind_group_layout,
+                &uniform_buf,
+                &spritesheet_sampler,
+                &texels,
+            );
 
             // Create the render pipeline
             let vs_bytes = helper::load_glsl(
@@ -207,10 +159,14 @@ impl GameRenderer {
                 sample_count: 1,
             });
 
-            Material {
-                bind_group,
-                pipeline,
-            }
+            (
+                Material {
+                    bind_group,
+                    pipeline,
+                },
+                bind_group_layout,
+                spritesheet_sampler,
+            )
         };
 
         let box_outline_mat = {
@@ -338,12 +294,99 @@ impl GameRenderer {
             index_count: index_data.len(),
             uniform_buf,
             spritesheet_mat,
+            spritesheet_layout,
+            spritesheet_sampler,
             box_outline_mat,
             depth: depth_texture.create_default_view(),
             clear_color: Color::BLACK,
         }
     }
 
+    /// Uploads the spritesheet to the GPU, and binds it for the spritesheet material.
+    fn spritesheet_bind_group(
+        device: &mut Device,
+        encoder: &mut Encoder,
+        bind_group_layout: &wgpu::BindGroupLayout,
+        uniform_buf: &wgpu::Buffer,
+        sampler: &wgpu::Sampler,
+        texels: &RgbaImage,
+    ) -> wgpu::BindGroup {
+        let texture_extent = wgpu::Extent3d {
+            width: texels.width(),
+            height: texels.height(),
+            depth: 1,
+        };
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            size: texture_extent,
+            array_layer_count: 1,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8Unorm,
+            usage: wgpu::TextureUsage::SAMPLED | wgpu::TextureUsage::TRANSFER_DST,
+        });
+        let texture_view = texture.create_default_view();
+        let temp_buf = device
+            .create_buffer_mapped(texels.len(), wgpu::BufferUsage::TRANSFER_SRC)
+            .fill_from_slice(&texels);
+        encoder.copy_buffer_to_texture(
+            wgpu::BufferCopyView {
+                buffer: &temp_buf,
+                offset: 0,
+                row_pitch: 4 * texels.width(),
+                image_height: texels.height(),
+            },
+            wgpu::TextureCopyView {
+                texture: &texture,
+                mip_level: 0,
+                array_layer: 0,
+                origin: wgpu::Origin3d {
+                    x: 0.0,
+                    y: 0.0,
+                    z: 0.0,
+                },
+            },
+            texture_extent,
+        );
+
+        // Create bind group
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: bind_group_layout,
+            bindings: &[
+                wgpu::Binding {
+                    binding: 0,
+                    resource: wgpu::BindingResource::Buffer {
+                        buffer: uniform_buf,
+                        range: 0..64,
+                    },
+                },
+                wgpu::Binding {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&texture_view),
+                },
+                wgpu::Binding {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(sampler),
+                },
+            ],
+        })
+    }
+
+    /// Swaps the spritesheet out for a new one, i.e. when an artist changes it.
+    pub fn reload_spritesheet(&mut self, texels: &RgbaImage, device: &mut Device) {
+        let mut encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { todo: 0 });
+        self.spritesheet_mat.bind_group = Self::spritesheet_bind_group(
+            device,
+            &mut encoder,
+            &self.spritesheet_layout,
+            &self.uniform_buf,
+            &self.spritesheet_sampler,
+            texels,
+        );
+        device.get_queue().submit(&[encoder.finish()]);
+    }
+
     fn update_clear_color(&mut self, world: &specs::World) {
         use specs::Join;
         let cam_fs = world.read_storage::<CameraFocus>();
@@ -442,7 +485,9 @@ impl GameRenderer {
         }
 
         let material = &self.box_outline_mat;
-        let verts = BoxOutlineVertex::get_from_ecs(world);
+        let mut verts = BoxOutlineVertex::get_from_ecs(world);
+        // health bars are just filled in outlines
+        verts.append(&mut BoxOutlineVertex::health_bars_from_ecs(world));
         rpass.set_pipeline(&material.pipeline);
         rpass.set_bind_group(0, &material.bind_group, &[]);
         for vertex_data in verts.iter() {
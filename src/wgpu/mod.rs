@@ -7,9 +7,12 @@ pub use draw_data::*;
 use game_renderer::GameRenderer;
 
 pub static IMG_BYTES: &[u8] = include_bytes!("../img/spritesheet.png");
+// the spritesheet is baked into the binary, but it's read from here when it's hot reloaded.
+pub const SPRITESHEET_PATH: &str = "./src/img/spritesheet.png";
 
 pub struct WgpuState {
     game_renderer: GameRenderer,
+    nuklear_state: NuklearState,
     imgui_renderer: imgui_wgpu::Renderer,
     device: wgpu::Device,
     surface: wgpu::Surface,
@@ -53,6 +56,9 @@ impl WgpuState {
         };
         let game_renderer = GameRenderer::init(texels, &swap_chain_descriptor, &mut device);
 
+        //game ui renderer
+        let nuklear_state = NuklearState::new(&mut device);
+
         //imgui renderer
         let format = wgpu::TextureFormat::Bgra8Unorm;
         let imgui_renderer = imgui_wgpu::Renderer::new(imgui, &mut device, format, None)
@@ -61,6 +67,7 @@ impl WgpuState {
         (
             Self {
                 game_renderer,
+                nuklear_state,
                 imgui_renderer,
                 device,
                 surface,
@@ -71,6 +78,12 @@ impl WgpuState {
         )
     }
 
+    /// Hands the game renderer a new spritesheet to draw with.
+    pub fn reload_spritesheet(&mut self, texels: &image::RgbaImage) {
+        self.game_renderer
+            .reload_spritesheet(texels, &mut self.device);
+    }
+
     #[inline]
     fn resize_if_should(&mut self, world: &specs::World) {
         let ls = world.read_resource::<LocalState>();
@@ -104,6 +117,18 @@ impl WgpuState {
             .render(world, &mut self.device, &mut encoder, &frame.view)
             .expect("game rendering failed");
 
+        // the game's UI goes on top of the game
+        self.nuklear_state.render(
+            world,
+            &mut self.device,
+            &mut encoder,
+            &frame.view,
+            (
+                self.swap_chain_descriptor.width,
+                self.swap_chain_descriptor.height,
+            ),
+        );
+
         // and now let's imgui_render
         self.imgui_renderer
             .render(ui, &mut self.device, &mut encoder, &frame.view)
@@ -1,8 +1,10 @@
 // use crate::prelude::*;
 use crate::prelude::*;
 use std::collections::HashSet;
+use std::time::Instant;
 use winit::{
-    dpi::LogicalSize, CreationError, Event, EventsLoop, VirtualKeyCode, Window, WindowBuilder,
+    dpi::LogicalSize, CreationError, Event, EventsLoop, MouseButton, VirtualKeyCode, Window,
+    WindowBuilder,
 };
 
 #[derive(Debug)]
@@ -10,6 +12,8 @@ pub struct WinitState {
     pub events_loop: EventsLoop,
     pub window: Window,
     pub keys_held: HashSet<VirtualKeyCode>,
+    pub mouse_buttons_held: HashSet<MouseButton>,
+    pub last_update: Instant,
 }
 
 impl WinitState {
@@ -28,18 +32,21 @@ impl WinitState {
             events_loop,
             window,
             keys_held: HashSet::new(),
+            mouse_buttons_held: HashSet::new(),
+            last_update: Instant::now(),
         })
     }
 
-    pub fn input(&mut self, world: &specs::World, dev_ui: &mut DevUiState) {
-        // gotta store that input somewhere, so the rest of the program can access it! :D
-        let mut local_state = world.write_resource::<LocalState>();
+    /// Polls the window for this frame's input.
+    /// It's up to the caller to hand it to LocalState, so that it can be recorded or replaced.
+    pub fn input(&mut self, dev_ui: &mut DevUiState) -> UserInput {
         let mut input_frame = UserInput::default();
 
         // manually split borrow
         let events_loop = &mut self.events_loop;
         let window = &self.window;
         let keys_held = &mut self.keys_held;
+        let mouse_buttons_held = &mut self.mouse_buttons_held;
 
         // sometimes I wonder why imgui doesn't just record this and be done with it.
         let dpi_factor = self.window.get_hidpi_factor().round();
@@ -64,13 +71,22 @@ impl WinitState {
                     | winit::WindowEvent::MouseWheel { .. }
                         if io.want_capture_mouse => {}
 
-                    _ => input_frame.process_event(&win_event, keys_held),
+                    _ => input_frame.process_event(&win_event, keys_held, mouse_buttons_held),
                 };
             }
         });
 
         input_frame.keys_held = keys_held.clone();
-        local_state.update_from_input(input_frame);
+        input_frame.mouse_buttons_held = mouse_buttons_held.clone();
+
+        // the frame's duration is recorded here, rather than in LocalState,
+        // so that anything else producing UserInputs can pick its own.
+        let now = Instant::now();
+        let duration = now.duration_since(self.last_update);
+        input_frame.seconds = duration.as_secs() as f32 + duration.subsec_nanos() as f32 * 1e-9;
+        self.last_update = now;
+
+        input_frame
     }
 }
 